@@ -1,7 +1,8 @@
+use std::collections::HashMap;
 use std::io::{self, Write, Read};
 use std::net::TcpStream;
 use uuid::Uuid;
-use chronos::parser::{self, Command};
+use chronos::parser::{self, Command, Filter};
 
 const HOST: &str = "127.0.0.1:9000";
 const VECTOR_DIM: usize = 128;
@@ -13,20 +14,157 @@ const OP_SEARCH: u8     = 0x03;
 const OP_HISTORY: u8    = 0x04;
 const OP_DELETE: u8     = 0x05;
 const OP_GET_AS_OF: u8  = 0x07;
+const OP_SEARCH_FILTERED: u8 = 0x09;
+const OP_BATCH: u8      = 0x0A;
+
+/// Redirects a write will follow before giving up, each hop costing one
+/// round trip to the node that told us who the real leader is.
+const MAX_LEADER_REDIRECTS: u32 = 3;
+
+/// Outcome of one `send_write` hop, decoded from the wire tag before
+/// `send_write`'s retry loop decides what to do with it.
+enum WriteOutcome {
+    Ok,
+    Redirect(String),
+    NoLeaderKnown,
+    Rejected,
+    Unexpected,
+}
+
+/// Tracks the cluster's node addresses, who the current Raft leader is
+/// (once a write tells us), and a small pool of already-open `TcpStream`s
+/// keyed by address - so a REPL session pays the TCP handshake once per
+/// node instead of once per command. Reads can go to any known node;
+/// writes are sent to the cached leader and retried against whatever node
+/// a server's `LR` (leader redirect) response points at, mirroring
+/// openraft's own `ForwardToLeader` client-retry loop.
+struct ClusterClient {
+    known_addrs: Vec<String>,
+    leader_addr: Option<String>,
+    conns: HashMap<String, TcpStream>,
+}
+
+impl ClusterClient {
+    fn new(known_addrs: Vec<String>) -> Self {
+        let leader_addr = known_addrs.first().cloned();
+        Self { known_addrs, leader_addr, conns: HashMap::new() }
+    }
+
+    /// Any node works for a read - just use the first one we know about.
+    fn read_addr(&self) -> &str {
+        &self.known_addrs[0]
+    }
+
+    /// Runs `op` against a pooled connection to `addr`, opening one if the
+    /// pool is empty for that address. If a *reused* connection errors
+    /// (the other end likely restarted or dropped it), one fresh connection
+    /// is transparently opened and `op` is retried before giving up - a
+    /// brand-new connection failing is reported straight away. The
+    /// connection is returned to the pool only after `op` succeeds.
+    fn with_connection<T>(&mut self, addr: &str, mut op: impl FnMut(&mut TcpStream) -> io::Result<T>) -> Result<T, String> {
+        let pooled = self.conns.remove(addr);
+        let reused = pooled.is_some();
+        let mut stream = match pooled {
+            Some(s) => s,
+            None => TcpStream::connect(addr).map_err(|e| format!("Could not reach {}: {}", addr, e))?,
+        };
+
+        match op(&mut stream) {
+            Ok(val) => {
+                self.conns.insert(addr.to_string(), stream);
+                Ok(val)
+            }
+            Err(_) if reused => {
+                let mut fresh = TcpStream::connect(addr).map_err(|e| format!("Could not reach {}: {}", addr, e))?;
+                let val = op(&mut fresh).map_err(|e| format!("{} (after reconnecting to {})", e, addr))?;
+                self.conns.insert(addr.to_string(), fresh);
+                Ok(val)
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Sends a single framed write request, following `LR` redirects to the
+    /// real leader (caching it for next time) up to `MAX_LEADER_REDIRECTS`.
+    fn send_write(&mut self, op: u8, body: &[u8]) -> Result<(), String> {
+        let mut addr = self.leader_addr.clone().unwrap_or_else(|| self.known_addrs[0].clone());
+
+        for _ in 0..=MAX_LEADER_REDIRECTS {
+            let outcome = self.with_connection(&addr, |stream| {
+                stream.write_all(&[op])?;
+                stream.write_all(&(body.len() as u32).to_le_bytes())?;
+                stream.write_all(body)?;
+
+                let mut tag = [0u8; 2];
+                stream.read_exact(&mut tag)?;
+
+                match &tag {
+                    b"OK" => Ok(WriteOutcome::Ok),
+                    b"LR" => {
+                        let mut len_buf = [0u8; 4];
+                        stream.read_exact(&mut len_buf)?;
+                        let len = u32::from_le_bytes(len_buf) as usize;
+                        if len == 0 {
+                            return Ok(WriteOutcome::NoLeaderKnown);
+                        }
+                        let mut addr_buf = vec![0u8; len];
+                        stream.read_exact(&mut addr_buf)?;
+                        Ok(WriteOutcome::Redirect(String::from_utf8_lossy(&addr_buf).to_string()))
+                    }
+                    b"ER" => {
+                        // Matches the `LR` framing: a length-prefixed (empty) tail.
+                        let mut len_buf = [0u8; 4];
+                        stream.read_exact(&mut len_buf)?;
+                        Ok(WriteOutcome::Rejected)
+                    }
+                    _ => Ok(WriteOutcome::Unexpected),
+                }
+            })?;
+
+            match outcome {
+                WriteOutcome::Ok => {
+                    self.leader_addr = Some(addr);
+                    return Ok(());
+                }
+                WriteOutcome::Redirect(new_addr) => {
+                    println!("[\u{21bb}] Not the leader - redirected to {}", new_addr);
+                    if !self.known_addrs.contains(&new_addr) {
+                        self.known_addrs.push(new_addr.clone());
+                    }
+                    addr = new_addr;
+                }
+                WriteOutcome::NoLeaderKnown => {
+                    return Err("No leader known cluster-wide right now; try again shortly".into());
+                }
+                WriteOutcome::Rejected => return Err("Server Rejected Request".into()),
+                WriteOutcome::Unexpected => return Err("Unexpected server response".into()),
+            }
+        }
+
+        Err(format!("Gave up after {} leader redirects", MAX_LEADER_REDIRECTS))
+    }
+}
 
 fn main() {
     print_banner();
 
-    match TcpStream::connect(HOST) {
-        Ok(_) => println!("[\u{2713}] Connected to ChronosDB at {}!", HOST),
+    let known_addrs: Vec<String> = std::env::args().skip(1).collect();
+    let known_addrs = if known_addrs.is_empty() { vec![HOST.to_string()] } else { known_addrs };
+
+    match TcpStream::connect(&known_addrs[0]) {
+        Ok(_) => println!("[\u{2713}] Connected to ChronosDB at {}!", known_addrs[0]),
         Err(_) => {
-            println!("[\u{2717}] Could not connect to server at {}.", HOST);
+            println!("[\u{2717}] Could not connect to server at {}.", known_addrs[0]);
             println!("    Make sure to run 'cargo run --release' in another terminal.");
             return;
         }
     }
+    if known_addrs.len() > 1 {
+        println!("Cluster nodes: {}", known_addrs.join(", "));
+    }
     println!("Type 'HELP' for supported commands or 'EXIT' to quit.\n");
 
+    let mut cluster = ClusterClient::new(known_addrs);
     let stdin = io::stdin();
     let mut buffer = String::new();
 
@@ -40,7 +178,7 @@ fn main() {
 
         match parser::parse_command(&buffer) {
             Ok(cmd) => {
-                if let Err(e) = execute_command(cmd) {
+                if let Err(e) = execute_command(cmd, &mut cluster) {
                     println!("[\u{26a0}\u{fe0f} Error] {}", e);
                 }
             }
@@ -66,27 +204,33 @@ fn print_help() {
     println!("\n--- Available Commands ---");
     println!("1. INSERT:      INSERT INTO VECTORS VALUES ([0.1, ...], \"payload\")");
     println!("2. SEARCH:      SELECT FROM VECTORS WHERE VECTOR NEAR [0.1, ...] LIMIT 5");
+    println!("2b. FILTERED:   FIND VECTOR NEAR [0.1, ...] WHERE payload.category = \"docs\" AND payload.score > 0.8 LIMIT 10");
     println!("3. GET:         GET 'uuid'");
     println!("4. HISTORY:     HISTORY 'uuid'");
     println!("5. TIME TRAVEL: SELECT FROM VECTORS WHERE ID='uuid' AS OF 1234567890");
     println!("6. UPDATE:      UPDATE VECTORS SET PAYLOAD=\"new\" WHERE ID='uuid'");
     println!("7. DELETE:      DELETE FROM VECTORS WHERE ID='uuid'");
-    println!("8. EXIT:        Quit\n");
+    println!("8. LOAD:        LOAD FILE \"inserts.txt\" (one INSERT per line, sent as one batch)");
+    println!("9. EXIT:        Quit\n");
 }
 
-fn execute_command(cmd: Command) -> Result<(), String> {
+fn execute_command(cmd: Command, cluster: &mut ClusterClient) -> Result<(), String> {
     match cmd {
         Command::Help => { print_help(); Ok(()) },
-        Command::Insert { vector, payload, id } => perform_insert(vector, payload, id),
+        Command::Insert { vector, payload, id } => perform_insert(cluster, vector, payload, id),
 
         // Route SELECT commands to either Time Travel or Vector Search
-        Command::Select { vector, filter_id, as_of, limit } => {
+        Command::Select { vector, filter_id, filter, as_of, limit } => {
             if let (Some(id), Some(ts)) = (filter_id, as_of) {
                 // Case 1: Time Travel Query (ID + AS OF)
-                perform_get_as_of(id, ts)
+                perform_get_as_of(cluster, id, ts)
             } else if let Some(vec) = vector {
-                // Case 2: Vector Search
-                perform_search(vec, limit)
+                match filter {
+                    // Case 2: Metadata-Filtered Vector Search
+                    Some(f) => perform_filtered_search(cluster, vec, f, limit),
+                    // Case 3: Plain Vector Search
+                    None => perform_search(cluster, vec, limit),
+                }
             } else {
                 Err("SELECT requires either 'WHERE VECTOR NEAR...' or 'WHERE ID=... AS OF...'".into())
             }
@@ -95,166 +239,289 @@ fn execute_command(cmd: Command) -> Result<(), String> {
         Command::Update { id, payload, .. } => {
             let dummy_vec = vec![0.0; VECTOR_DIM];
             if let Some(p) = payload {
-                perform_insert(dummy_vec, p, Some(id))
+                perform_insert(cluster, dummy_vec, p, Some(id))
             } else {
                 Err("Update requires a payload.".into())
             }
         },
-        Command::Delete { id } => perform_delete(id),
-        Command::Get { id } => perform_get(id),
-        Command::History { id } => perform_history(id),
+        Command::Delete { id } => perform_delete(cluster, id),
+        Command::Get { id } => perform_get(cluster, id),
+        Command::History { id } => perform_history(cluster, id),
+        Command::Load { path } => perform_load(cluster, path),
         Command::Exit => std::process::exit(0),
     }
 }
 
 // --- NETWORK HANDLERS ---
 
-fn perform_insert(mut vector: Vec<f32>, payload: String, explicit_id: Option<Uuid>) -> Result<(), String> {
+// Protocol: [UUID (16b)] [vector: VECTOR_DIM * f32] [sort_key_len: u32][sort_key] [payload: remainder]
+// No sort key from the CLI yet, so the length-prefixed field is always empty.
+// Shared by `perform_insert` (one OP_INSERT request per record, over the
+// pooled connection) and `perform_load`
+// (many of these framed as OP_INSERT sub-items inside a single OP_BATCH).
+fn encode_insert_body(id: Uuid, vector: &[f32], payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(16 + (vector.len() * 4) + 4 + payload.len());
+    body.extend_from_slice(id.as_bytes());
+    for f in vector { body.extend_from_slice(&f.to_le_bytes()); }
+    body.extend_from_slice(&0u32.to_le_bytes());
+    body.extend_from_slice(payload);
+    body
+}
+
+fn perform_insert(cluster: &mut ClusterClient, mut vector: Vec<f32>, payload: String, explicit_id: Option<Uuid>) -> Result<(), String> {
     if vector.len() > VECTOR_DIM { return Err(format!("Vector too long (Max {})", VECTOR_DIM)); }
     vector.resize(VECTOR_DIM, 0.0);
 
-    let mut stream = TcpStream::connect(HOST).map_err(|e| e.to_string())?;
     let id = explicit_id.unwrap_or_else(Uuid::new_v4);
-    let payload_bytes = payload.as_bytes();
-    let total_len = (16 + (VECTOR_DIM * 4) + payload_bytes.len()) as u32;
-
-    stream.write_all(&[OP_INSERT]).unwrap();
-    stream.write_all(&total_len.to_le_bytes()).unwrap();
-    stream.write_all(id.as_bytes()).unwrap();
-    for f in vector { stream.write_all(&f.to_le_bytes()).unwrap(); }
-    stream.write_all(payload_bytes).unwrap();
-
-    let mut resp = [0u8; 2];
-    stream.read_exact(&mut resp).unwrap();
-    if &resp == b"OK" {
-        println!("[\u{2713} OK] Inserted ID: {}", id);
-        Ok(())
-    } else {
-        Err("Server Rejected Request".into())
+    let body = encode_insert_body(id, &vector, payload.as_bytes());
+    cluster.send_write(OP_INSERT, &body)?;
+    println!("[\u{2713} OK] Inserted ID: {}", id);
+    Ok(())
+}
+
+// Reads every `INSERT` statement out of `path`, one per line, and ships them
+// all as a single OP_BATCH request - one Raft round trip for the whole file
+// instead of `perform_insert`'s one OP_INSERT round trip per record.
+// Sent straight to the cached leader (no `LR` redirect handling yet - OP_BATCH's
+// framing doesn't carry one); if that guess is wrong, re-run LOAD once a
+// single INSERT has updated `cluster`'s leader cache.
+fn perform_load(cluster: &mut ClusterClient, path: String) -> Result<(), String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Could not read '{}': {}", path, e))?;
+
+    let mut items: Vec<(Uuid, Vec<u8>)> = Vec::new();
+    for (lineno, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        match parser::parse_command(line) {
+            Ok(Command::Insert { mut vector, payload, id }) => {
+                if vector.len() > VECTOR_DIM {
+                    return Err(format!("Line {}: vector too long (Max {})", lineno + 1, VECTOR_DIM));
+                }
+                vector.resize(VECTOR_DIM, 0.0);
+                let record_id = id.unwrap_or_else(Uuid::new_v4);
+                items.push((record_id, encode_insert_body(record_id, &vector, payload.as_bytes())));
+            }
+            Ok(_) => return Err(format!("Line {}: LOAD only supports INSERT statements", lineno + 1)),
+            Err(e) => return Err(format!("Line {}: {}", lineno + 1, e)),
+        }
     }
+
+    if items.is_empty() {
+        println!("[\u{2139}\u{fe0f}] Nothing to load - '{}' had no INSERT statements.", path);
+        return Ok(());
+    }
+
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&(items.len() as u32).to_le_bytes());
+    for (_, body) in &items {
+        frame.push(OP_INSERT);
+        frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        frame.extend_from_slice(body);
+    }
+
+    let addr = cluster.leader_addr.clone().unwrap_or_else(|| cluster.known_addrs[0].clone());
+    let (ok_count, statuses) = cluster.with_connection(&addr, |stream| {
+        stream.write_all(&[OP_BATCH])?;
+        stream.write_all(&(frame.len() as u32).to_le_bytes())?;
+        stream.write_all(&frame)?;
+
+        let mut count_buf = [0u8; 4];
+        stream.read_exact(&mut count_buf)?;
+        let count = u32::from_le_bytes(count_buf) as usize;
+
+        let mut statuses = Vec::with_capacity(count);
+        let mut ok_count = 0;
+        for _ in 0..count {
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status)?;
+            let ok = &status == b"OK";
+            if ok { ok_count += 1; }
+            statuses.push(ok);
+        }
+        Ok((ok_count, statuses))
+    })?;
+
+    if statuses.len() != items.len() {
+        return Err("Server rejected the batch (malformed request frame or failed Raft write)".into());
+    }
+    for ((id, _), ok) in items.iter().zip(statuses.iter()) {
+        if !ok { println!("  [\u{2717}] {} failed", id); }
+    }
+
+    println!("[\u{2713} OK] Loaded {}/{} records from '{}'", ok_count, items.len(), path);
+    Ok(())
 }
 
-fn perform_search(mut vector: Vec<f32>, limit: usize) -> Result<(), String> {
+fn perform_search(cluster: &mut ClusterClient, mut vector: Vec<f32>, limit: usize) -> Result<(), String> {
     vector.resize(VECTOR_DIM, 0.0);
-    let mut stream = TcpStream::connect(HOST).map_err(|e| e.to_string())?;
     let total_len = (4 + (VECTOR_DIM * 4)) as u32;
+    let addr = cluster.read_addr().to_string();
+
+    let results = cluster.with_connection(&addr, |stream| {
+        stream.write_all(&[OP_SEARCH])?;
+        stream.write_all(&total_len.to_le_bytes())?;
+        stream.write_all(&(limit as u32).to_le_bytes())?;
+        for f in &vector { stream.write_all(&f.to_le_bytes())?; }
+
+        let mut count_buf = [0u8; 4];
+        stream.read_exact(&mut count_buf)?;
+        let count = u32::from_le_bytes(count_buf);
+
+        let mut results = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut uuid_buf = [0u8; 16];
+            stream.read_exact(&mut uuid_buf)?;
+            let mut dist_buf = [0u8; 4];
+            stream.read_exact(&mut dist_buf)?;
+            results.push((Uuid::from_bytes(uuid_buf), f32::from_le_bytes(dist_buf).sqrt()));
+        }
+        Ok(results)
+    })?;
 
-    stream.write_all(&[OP_SEARCH]).unwrap();
-    stream.write_all(&total_len.to_le_bytes()).unwrap();
-    stream.write_all(&(limit as u32).to_le_bytes()).unwrap();
-    for f in vector { stream.write_all(&f.to_le_bytes()).unwrap(); }
-
-    let mut count_buf = [0u8; 4];
-    stream.read_exact(&mut count_buf).unwrap();
-    let count = u32::from_le_bytes(count_buf);
-
-    println!("\nFound {} matches:", count);
-    for _ in 0..count {
-        let mut uuid_buf = [0u8; 16];
-        stream.read_exact(&mut uuid_buf).unwrap();
-        let mut dist_buf = [0u8; 4];
-        stream.read_exact(&mut dist_buf).unwrap();
-        let dist = f32::from_le_bytes(dist_buf).sqrt();
-        println!("  • {} (Dist: {:.4})", Uuid::from_bytes(uuid_buf), dist);
+    println!("\nFound {} matches:", results.len());
+    for (id, dist) in results {
+        println!("  • {} (Dist: {:.4})", id, dist);
     }
     println!();
     Ok(())
 }
 
-fn perform_get(id: Uuid) -> Result<(), String> {
-    let mut stream = TcpStream::connect(HOST).map_err(|e| e.to_string())?;
-    stream.write_all(&[OP_GET]).unwrap();
-    stream.write_all(&16u32.to_le_bytes()).unwrap();
-    stream.write_all(id.as_bytes()).unwrap();
+fn perform_filtered_search(cluster: &mut ClusterClient, mut vector: Vec<f32>, filter: Filter, limit: usize) -> Result<(), String> {
+    vector.resize(VECTOR_DIM, 0.0);
 
-    let mut found = [0u8; 1];
-    stream.read_exact(&mut found).unwrap();
+    let mut filter_bytes = Vec::new();
+    filter.encode(&mut filter_bytes);
+
+    let total_len = (4 + (VECTOR_DIM * 4) + filter_bytes.len()) as u32;
+    let addr = cluster.read_addr().to_string();
+
+    let results = cluster.with_connection(&addr, |stream| {
+        stream.write_all(&[OP_SEARCH_FILTERED])?;
+        stream.write_all(&total_len.to_le_bytes())?;
+        stream.write_all(&(limit as u32).to_le_bytes())?;
+        for f in &vector { stream.write_all(&f.to_le_bytes())?; }
+        stream.write_all(&filter_bytes)?;
+
+        let mut count_buf = [0u8; 4];
+        stream.read_exact(&mut count_buf)?;
+        let count = u32::from_le_bytes(count_buf);
+
+        let mut results = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut uuid_buf = [0u8; 16];
+            stream.read_exact(&mut uuid_buf)?;
+            let mut dist_buf = [0u8; 4];
+            stream.read_exact(&mut dist_buf)?;
+            results.push((Uuid::from_bytes(uuid_buf), f32::from_le_bytes(dist_buf)));
+        }
+        Ok(results)
+    })?;
+
+    println!("\nFound {} matches:", results.len());
+    for (id, dist) in results {
+        println!("  • {} (Dist: {:.4})", id, dist);
+    }
+    println!();
+    Ok(())
+}
+
+fn perform_get(cluster: &mut ClusterClient, id: Uuid) -> Result<(), String> {
+    let addr = cluster.read_addr().to_string();
+    let payload = cluster.with_connection(&addr, |stream| {
+        stream.write_all(&[OP_GET])?;
+        stream.write_all(&16u32.to_le_bytes())?;
+        stream.write_all(id.as_bytes())?;
+
+        let mut found = [0u8; 1];
+        stream.read_exact(&mut found)?;
+        if found[0] != 1 { return Ok(None); }
 
-    if found[0] == 1 {
         let mut len_buf = [0u8; 4];
-        stream.read_exact(&mut len_buf).unwrap();
+        stream.read_exact(&mut len_buf)?;
         let len = u32::from_le_bytes(len_buf) as usize;
         let mut payload = vec![0u8; len];
-        stream.read_exact(&mut payload).unwrap();
-        println!("Payload: \"{}\"", String::from_utf8_lossy(&payload));
-        Ok(())
-    } else {
-        println!("[\u{2717}] ID Not Found.");
-        Ok(())
+        stream.read_exact(&mut payload)?;
+        Ok(Some(payload))
+    })?;
+
+    match payload {
+        Some(payload) => println!("Payload: \"{}\"", String::from_utf8_lossy(&payload)),
+        None => println!("[\u{2717}] ID Not Found."),
     }
+    Ok(())
 }
 
-fn perform_get_as_of(id: Uuid, timestamp: u64) -> Result<(), String> {
-    let mut stream = TcpStream::connect(HOST).map_err(|e| e.to_string())?;
+fn perform_get_as_of(cluster: &mut ClusterClient, id: Uuid, timestamp: u64) -> Result<(), String> {
+    let addr = cluster.read_addr().to_string();
 
     // Body: [UUID (16)] [Timestamp (8)]
     // Total Len: 24 bytes
-    stream.write_all(&[OP_GET_AS_OF]).unwrap();
-    stream.write_all(&24u32.to_le_bytes()).unwrap();
-    stream.write_all(id.as_bytes()).unwrap();
-    stream.write_all(&timestamp.to_le_bytes()).unwrap();
+    let payload = cluster.with_connection(&addr, |stream| {
+        stream.write_all(&[OP_GET_AS_OF])?;
+        stream.write_all(&24u32.to_le_bytes())?;
+        stream.write_all(id.as_bytes())?;
+        stream.write_all(&timestamp.to_le_bytes())?;
 
-    let mut found = [0u8; 1];
-    stream.read_exact(&mut found).unwrap();
+        let mut found = [0u8; 1];
+        stream.read_exact(&mut found)?;
+        if found[0] != 1 { return Ok(None); }
 
-    if found[0] == 1 {
         let mut len_buf = [0u8; 4];
-        stream.read_exact(&mut len_buf).unwrap();
+        stream.read_exact(&mut len_buf)?;
         let len = u32::from_le_bytes(len_buf) as usize;
         let mut payload = vec![0u8; len];
-        stream.read_exact(&mut payload).unwrap();
-
-        println!("[\u{23f1}\u{fe0f} Time Travel] Record state at {}:", timestamp);
-        println!("Payload: \"{}\"", String::from_utf8_lossy(&payload));
-        Ok(())
-    } else {
-        println!("[\u{2717}] No record found valid at time {}.", timestamp);
-        Ok(())
+        stream.read_exact(&mut payload)?;
+        Ok(Some(payload))
+    })?;
+
+    match payload {
+        Some(payload) => {
+            println!("[\u{23f1}\u{fe0f} Time Travel] Record state at {}:", timestamp);
+            println!("Payload: \"{}\"", String::from_utf8_lossy(&payload));
+        }
+        None => println!("[\u{2717}] No record found valid at time {}.", timestamp),
     }
+    Ok(())
 }
 
-fn perform_history(id: Uuid) -> Result<(), String> {
-    let mut stream = TcpStream::connect(HOST).map_err(|e| e.to_string())?;
-    stream.write_all(&[OP_HISTORY]).unwrap();
-    stream.write_all(&16u32.to_le_bytes()).unwrap();
-    stream.write_all(id.as_bytes()).unwrap();
-
-    let mut count_buf = [0u8; 4];
-    stream.read_exact(&mut count_buf).unwrap();
-    let count = u32::from_le_bytes(count_buf);
+fn perform_history(cluster: &mut ClusterClient, id: Uuid) -> Result<(), String> {
+    let addr = cluster.read_addr().to_string();
+    let versions = cluster.with_connection(&addr, |stream| {
+        stream.write_all(&[OP_HISTORY])?;
+        stream.write_all(&16u32.to_le_bytes())?;
+        stream.write_all(id.as_bytes())?;
+
+        let mut count_buf = [0u8; 4];
+        stream.read_exact(&mut count_buf)?;
+        let count = u32::from_le_bytes(count_buf);
+
+        let mut versions = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut start_buf = [0u8; 8];
+            stream.read_exact(&mut start_buf)?;
+            let mut end_buf = [0u8; 8];
+            stream.read_exact(&mut end_buf)?;
+            let mut len_buf = [0u8; 4];
+            stream.read_exact(&mut len_buf)?;
+            let len = u32::from_le_bytes(len_buf) as usize;
+            let mut payload = vec![0u8; len];
+            stream.read_exact(&mut payload)?;
+            versions.push((u64::from_le_bytes(start_buf), u64::from_le_bytes(end_buf), payload));
+        }
+        Ok(versions)
+    })?;
 
     println!("History for {}:", id);
-    for i in 0..count {
-        let mut start_buf = [0u8; 8];
-        stream.read_exact(&mut start_buf).unwrap();
-        let mut end_buf = [0u8; 8];
-        stream.read_exact(&mut end_buf).unwrap();
-        let mut len_buf = [0u8; 4];
-        stream.read_exact(&mut len_buf).unwrap();
-        let len = u32::from_le_bytes(len_buf) as usize;
-        let mut payload = vec![0u8; len];
-        stream.read_exact(&mut payload).unwrap();
-
-        let start = u64::from_le_bytes(start_buf);
-        let end = u64::from_le_bytes(end_buf);
-        let end_str = if end == u64::MAX { "PRESENT".to_string() } else { end.to_string() };
-        println!("  v{} | {} -> {} | \"{}\"", i+1, start, end_str, String::from_utf8_lossy(&payload));
+    for (i, (start, end, payload)) in versions.iter().enumerate() {
+        let end_str = if *end == u64::MAX { "PRESENT".to_string() } else { end.to_string() };
+        println!("  v{} | {} -> {} | \"{}\"", i+1, start, end_str, String::from_utf8_lossy(payload));
     }
     Ok(())
 }
 
-fn perform_delete(id: Uuid) -> Result<(), String> {
-    let mut stream = TcpStream::connect(HOST).map_err(|e| e.to_string())?;
-    stream.write_all(&[OP_DELETE]).unwrap();
-    stream.write_all(&16u32.to_le_bytes()).unwrap();
-    stream.write_all(id.as_bytes()).unwrap();
-
-    let mut resp = [0u8; 2];
-    stream.read_exact(&mut resp).unwrap();
-    if &resp == b"OK" {
-        println!("[\u{2713} OK] Deleted ID: {}", id);
-        Ok(())
-    } else {
-        Err("Delete Failed".into())
-    }
+fn perform_delete(cluster: &mut ClusterClient, id: Uuid) -> Result<(), String> {
+    cluster.send_write(OP_DELETE, id.as_bytes())?;
+    println!("[\u{2713} OK] Deleted ID: {}", id);
+    Ok(())
 }
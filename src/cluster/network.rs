@@ -10,16 +10,35 @@ use openraft::raft::{
 use openraft::{
     BasicNode, RaftNetwork, RaftNetworkFactory, RaftTypeConfig,
 };
+use rand::Rng;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use crate::cluster::types::{TypeConfig, NodeId};
 use std::fmt::{Display, Formatter};
-
-pub struct ChronosNetwork;
+use std::time::{Duration, Instant};
+
+// Connection-level tuning. Kept modest: Raft RPCs are small and frequent,
+// so we care more about not stalling the heartbeat/election timers than
+// about squeezing out every retry.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_RETRIES: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(20);
+
+pub struct ChronosNetwork {
+    client: reqwest::Client,
+}
 
 impl ChronosNetwork {
     pub fn new() -> Self {
-        Self
+        let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .pool_idle_timeout(Duration::from_secs(30))
+        .build()
+        .expect("Failed to build shared reqwest client");
+
+        Self { client }
     }
 }
 
@@ -34,6 +53,10 @@ impl RaftNetworkFactory<TypeConfig> for ChronosNetwork {
         ChronosNetworkConnection {
             addr: node.addr.clone(),
             _target,
+            // Cheap clone: reqwest::Client is an Arc handle around the
+            // connection pool, so every connection shares one pool instead
+            // of opening a fresh socket per RPC.
+            client: self.client.clone(),
         }
     }
 }
@@ -41,6 +64,7 @@ impl RaftNetworkFactory<TypeConfig> for ChronosNetwork {
 pub struct ChronosNetworkConnection {
     addr: String,
     _target: NodeId,
+    client: reqwest::Client,
 }
 
 impl ChronosNetworkConnection {
@@ -48,42 +72,66 @@ impl ChronosNetworkConnection {
         &self,
         route: &str,
         req: Req,
+        option: &RPCOption,
     ) -> Result<Resp, RPCError<NodeId, BasicNode, RaftError<NodeId, E>>>
     where
-    Req: Serialize,
+    Req: Serialize + Clone,
     Resp: DeserializeOwned,
     E: std::error::Error + 'static,
     {
         let url = format!("http://{}/{}", self.addr, route);
-        let client = reqwest::Client::new();
-
-        let resp = client
-        .post(&url)
-        .json(&req)
-        .send()
-        .await
-        .map_err(|e| {
-            RPCError::Network(
-                NetworkError::new(&AnyError(e.to_string())),
-            )
-        })?;
-
-        if !resp.status().is_success() {
-            return Err(RPCError::Network(NetworkError::new(
-                &AnyError(format!("HTTP error: {}", resp.status())),
-            )));
+        let deadline = option.hard_deadline();
+
+        let mut attempt = 0u32;
+        loop {
+            let resp = self.client.post(&url).json(&req).send().await;
+
+            match resp {
+                Ok(resp) => {
+                    if !resp.status().is_success() {
+                        return Err(RPCError::Network(NetworkError::new(
+                            &AnyError(format!("HTTP error: {}", resp.status())),
+                        )));
+                    }
+
+                    return resp.json::<Resp>().await.map_err(|e| {
+                        RPCError::Network(NetworkError::new(&AnyError(e.to_string())))
+                    });
+                }
+                // Only retry the failure modes a retry can plausibly fix -
+                // a refused/reset connection or a timed-out attempt. Other
+                // errors (bad JSON, TLS, etc.) are surfaced at once, without
+                // a retry; connect/timeout errors are surfaced once
+                // `MAX_RETRIES` retries are used up.
+                Err(e) if attempt < MAX_RETRIES && (e.is_connect() || e.is_timeout()) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(RPCError::Network(NetworkError::new(&AnyError(
+                            "RPC deadline exceeded during retry".to_string(),
+                        ))));
+                    }
+
+                    let backoff = backoff_with_jitter(attempt).min(remaining);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(RPCError::Network(NetworkError::new(&AnyError(e.to_string()))));
+                }
+            }
         }
-
-        let res = resp.json::<Resp>().await.map_err(|e| {
-            RPCError::Network(
-                NetworkError::new(&AnyError(e.to_string())),
-            )
-        })?;
-
-        Ok(res)
     }
 }
 
+/// Exponential backoff (`BASE_BACKOFF * 2^attempt`) plus up to 50% jitter,
+/// so a thundering herd of followers retrying a lost leader doesn't all
+/// retry in lockstep.
+fn backoff_with_jitter(attempt: u32) -> Duration {
+    let exp = BASE_BACKOFF * 2u32.saturating_pow(attempt);
+    let jitter_ms = rand::thread_rng().gen_range(0..=(exp.as_millis() as u64 / 2).max(1));
+    exp + Duration::from_millis(jitter_ms)
+}
+
 #[derive(Debug)]
 struct AnyError(String);
 
@@ -99,7 +147,7 @@ impl RaftNetwork<TypeConfig> for ChronosNetworkConnection {
     async fn append_entries(
         &mut self,
         req: AppendEntriesRequest<TypeConfig>,
-        _option: RPCOption,
+        option: RPCOption,
     ) -> Result<
     AppendEntriesResponse<NodeId>,
     RPCError<NodeId, BasicNode, RaftError<NodeId>>,
@@ -107,6 +155,7 @@ impl RaftNetwork<TypeConfig> for ChronosNetworkConnection {
         self.send_post::<_, _, openraft::error::Infallible>(
             "raft-append",
             req,
+            &option,
         )
         .await
     }
@@ -114,7 +163,7 @@ impl RaftNetwork<TypeConfig> for ChronosNetworkConnection {
     async fn install_snapshot(
         &mut self,
         req: InstallSnapshotRequest<TypeConfig>,
-        _option: RPCOption,
+        option: RPCOption,
     ) -> Result<
     InstallSnapshotResponse<NodeId>,
     RPCError<
@@ -123,13 +172,13 @@ impl RaftNetwork<TypeConfig> for ChronosNetworkConnection {
     RaftError<NodeId, InstallSnapshotError>,
     >,
     > {
-        self.send_post("raft-snapshot", req).await
+        self.send_post("raft-snapshot", req, &option).await
     }
 
     async fn vote(
         &mut self,
         req: VoteRequest<NodeId>,
-        _option: RPCOption,
+        option: RPCOption,
     ) -> Result<
     VoteResponse<NodeId>,
     RPCError<NodeId, BasicNode, RaftError<NodeId>>,
@@ -137,6 +186,7 @@ impl RaftNetwork<TypeConfig> for ChronosNetworkConnection {
         self.send_post::<_, _, openraft::error::Infallible>(
             "raft-vote",
             req,
+            &option,
         )
         .await
     }
@@ -12,6 +12,9 @@ pub enum ChronosRequest {
         id: Uuid,
         vector: Vec<f32>,
         payload: Vec<u8>,
+        /// Opaque key for `ChronosDb`'s secondary range index. Empty if the
+        /// caller didn't attach one.
+        sort_key: Vec<u8>,
         ts: u64
     },
     Delete {
@@ -22,12 +25,21 @@ pub enum ChronosRequest {
         payload: Vec<u8>,
         ts: u64
     },
+    /// A group of mutations committed as a single replicated log entry, so
+    /// bulk loads pay for one consensus round trip instead of one per
+    /// record. Applied as one unit in `apply_to_state_machine`, but an item
+    /// that fails does not roll back the items applied before it; each
+    /// item's outcome is reported in `ChronosResponse::item_results`.
+    Batch(Vec<ChronosRequest>),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChronosResponse {
     pub success: bool,
     pub message: String,
+    /// Per-item OK/failure for a `Batch` request, in submission order.
+    /// Empty for every other request variant.
+    pub item_results: Vec<bool>,
 }
 
 openraft::declare_raft_types!(
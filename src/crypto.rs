@@ -0,0 +1,132 @@
+// src/crypto.rs
+//
+// Optional AEAD encryption at rest for persisted indexes (`HnswIndex`) and
+// record payloads (`storage::Segment`), mirroring the server-side-encryption
+// designs where every stored object is sealed under an authenticated cipher
+// with its own unique nonce rather than one key/nonce for the whole file.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+use std::io;
+
+/// Random per-file salt mixed into the HKDF derivation, so two files opened
+/// with the same master key never share a data key.
+pub const SALT_LEN: usize = 16;
+/// XChaCha20's extended nonce - large enough to pick at random per-seal
+/// without worrying about birthday-bound collisions over a file's lifetime.
+pub const NONCE_LEN: usize = 24;
+/// Poly1305 authentication tag appended to every ciphertext.
+pub const TAG_LEN: usize = 16;
+
+/// Magic + version prefix that makes an encrypted file self-describing, so
+/// `load` can tell an encrypted file from a plaintext one before it has a
+/// key to try.
+pub const MAGIC: &[u8; 4] = b"CDBE"; // ChronosDB Encrypted
+pub const VERSION: u8 = 1;
+
+/// Binds derived keys to this crate's at-rest encryption scheme, so a
+/// master key shared with some other use can't be replayed against these
+/// files (and vice versa).
+const HKDF_INFO: &[u8] = b"chronos-db:aead-v1";
+
+/// A per-file key derived from a caller-supplied master key via HKDF-SHA256,
+/// so the master key itself is never written to disk or reused verbatim as
+/// a cipher key.
+#[derive(Clone)]
+pub struct DataKey(Key);
+
+impl std::fmt::Debug for DataKey {
+    /// Deliberately doesn't print the key material - only that one is set.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DataKey").field(&"<redacted>").finish()
+    }
+}
+
+impl DataKey {
+    /// Derives a data key from `master_key` and this file's `salt`.
+    pub fn derive(master_key: &[u8], salt: &[u8; SALT_LEN]) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(Some(salt), master_key);
+        let mut okm = [0u8; 32];
+        hkdf.expand(HKDF_INFO, &mut okm)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        DataKey(*Key::from_slice(&okm))
+    }
+}
+
+/// Generates a fresh random salt for a new encrypted file.
+pub fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+/// Encrypts `plaintext` under a fresh random nonce, returning
+/// `nonce || ciphertext || tag`. Called once per node block / per record so
+/// that no two sealed blocks in a file, or across files sharing a key,
+/// reuse a nonce.
+pub fn seal(key: &DataKey, plaintext: &[u8]) -> Vec<u8> {
+    let cipher = XChaCha20Poly1305::new(&key.0);
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let nonce = XNonce::from_slice(&nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .expect("XChaCha20-Poly1305 encryption of an in-memory buffer cannot fail");
+
+    let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+    out
+}
+
+/// Inverse of `seal`: splits the nonce back off `sealed` and verifies the
+/// Poly1305 tag. Fails cleanly with an `io::Error` - never panics - on a
+/// wrong key or tampered/corrupt bytes, the same way a CRC mismatch is
+/// surfaced elsewhere in this crate.
+pub fn open(key: &DataKey, sealed: &[u8]) -> io::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN + TAG_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "sealed block shorter than nonce + tag",
+        ));
+    }
+    let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(&key.0);
+    let nonce = XNonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "AEAD tag mismatch: wrong key or tampered data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_then_open_round_trips() {
+        let salt = random_salt();
+        let key = DataKey::derive(b"master-key", &salt);
+        let plaintext = b"the quick brown fox".to_vec();
+
+        let sealed = seal(&key, &plaintext);
+        let opened = open(&key, &sealed).unwrap();
+
+        assert_eq!(opened, plaintext);
+    }
+
+    #[test]
+    fn open_with_wrong_key_fails_cleanly() {
+        let salt = random_salt();
+        let key = DataKey::derive(b"master-key", &salt);
+        let wrong_key = DataKey::derive(b"a different master key", &salt);
+
+        let sealed = seal(&key, b"secret payload");
+
+        assert!(open(&wrong_key, &sealed).is_err());
+    }
+}
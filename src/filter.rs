@@ -1,40 +1,153 @@
-use bit_vec::BitVec;
 use seahash::hash;
+use std::io;
 
-pub struct BloomFilter {
-    bits: BitVec,
+/// A Bloom filter whose slots are small saturating counters instead of
+/// single bits, so a key can be un-inserted again: `remove` decrements the
+/// same `k` slots `insert` incremented, and `contains` only succeeds if all
+/// `k` slots are still nonzero. Each counter is 4 bits (0-15, saturating),
+/// packed two per byte to keep the memory overhead close to a plain bit-vector.
+///
+/// Caveat inherent to counting filters: decrementing a slot shared with an
+/// unrelated key (a hash collision) can spuriously clear that key's slot
+/// too. This is the standard counting-Bloom tradeoff - acceptable here
+/// because a false negative just falls back to a real lookup, same as any
+/// other Bloom miss.
+struct CountingBloom {
+    counters: Vec<u8>, // packed nibbles, len = ceil(m / 2)
+    m: usize,
     num_hashes: u32,
+    /// Count of counters currently nonzero, maintained incrementally by
+    /// `insert`/`remove` (a counter transitioning 0<->nonzero is the only
+    /// time it changes) so `fill_ratio` - on `BloomFilter::insert`'s hot
+    /// path - is an O(1) lookup instead of an O(m) rescan of every counter.
+    occupied: usize,
 }
 
+impl CountingBloom {
+    fn with_capacity(expected_items: usize, false_positive_rate: f64) -> Self {
+        // m = -(n * ln(p)) / (ln(2)^2), k = (m / n) * ln(2)
+        let ln2 = 2.0f64.ln();
+        let m = -((expected_items.max(1) as f64 * false_positive_rate.ln()) / (ln2 * ln2));
+        let k = (m / expected_items.max(1) as f64) * ln2;
+
+        Self::new(m.ceil() as usize, k.ceil().max(1.0) as u32)
+    }
+
+    fn new(m: usize, num_hashes: u32) -> Self {
+        let m = m.max(1);
+        Self {
+            counters: vec![0u8; (m + 1) / 2],
+            m,
+            num_hashes,
+            occupied: 0,
+        }
+    }
+
+    fn get(&self, idx: usize) -> u8 {
+        let byte = self.counters[idx / 2];
+        if idx % 2 == 0 { byte & 0x0F } else { (byte >> 4) & 0x0F }
+    }
+
+    fn set(&mut self, idx: usize, value: u8) {
+        let value = value.min(15);
+        let byte = &mut self.counters[idx / 2];
+        if idx % 2 == 0 {
+            *byte = (*byte & 0xF0) | value;
+        } else {
+            *byte = (*byte & 0x0F) | (value << 4);
+        }
+    }
+
+    fn slots(&self, key: &[u8]) -> Vec<usize> {
+        let (h1, h2) = Self::hash_pair(key);
+        let m = self.m as u64;
+        (0..self.num_hashes)
+        .map(|i| (h1.wrapping_add((i as u64).wrapping_mul(h2)) % m) as usize)
+        .collect()
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        for idx in self.slots(key) {
+            let v = self.get(idx);
+            if v == 0 {
+                self.occupied += 1;
+            }
+            if v < 15 {
+                self.set(idx, v + 1);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        for idx in self.slots(key) {
+            let v = self.get(idx);
+            if v == 1 {
+                self.occupied -= 1;
+            }
+            if v > 0 {
+                self.set(idx, v - 1);
+            }
+        }
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.slots(key).into_iter().all(|idx| self.get(idx) != 0)
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        self.occupied as f64 / self.m as f64
+    }
+
+    fn hash_pair(key: &[u8]) -> (u64, u64) {
+        let h1 = hash(key);
+        let h2 = h1.wrapping_add(0x9E3779B97F4A7C15); // Golden Ratio constant
+        (h1, h2)
+    }
+}
+
+/// Scalable Bloom filter: a sequence of `CountingBloom` stages. Once the
+/// active (last) stage's fill ratio crosses ~50%, a new, larger stage is
+/// appended with a tightened false-positive ratio (`r = 0.5` per stage, as
+/// in Almeida et al.'s Scalable Bloom Filters), so the overall error rate
+/// stays bounded as the item count grows past the original `expected_items`
+/// estimate instead of degrading silently.
+pub struct BloomFilter {
+    stages: Vec<CountingBloom>,
+    base_expected_items: usize,
+    base_error_rate: f64,
+}
+
+const GROWTH_FILL_THRESHOLD: f64 = 0.5;
+const TIGHTENING_RATIO: f64 = 0.5;
+
 impl BloomFilter {
     /// Create a new Bloom Filter.
     /// expected_items: How many items you plan to store.
     /// false_positive_rate: Acceptable error rate (e.g., 0.01 for 1%).
     pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
-        // Calculate optimal size (m) and hash count (k)
-        // m = -(n * ln(p)) / (ln(2)^2)
-        let ln2 = 2.0f64.ln();
-        let m = -((expected_items as f64 * false_positive_rate.ln()) / (ln2 * ln2));
-
-        // k = (m / n) * ln(2)
-        let k = (m / expected_items as f64) * ln2;
-
         Self {
-            bits: BitVec::from_elem(m.ceil() as usize, false),
-            num_hashes: k.ceil() as u32,
+            stages: vec![CountingBloom::with_capacity(expected_items, false_positive_rate)],
+            base_expected_items: expected_items,
+            base_error_rate: false_positive_rate,
         }
     }
 
-    /// Add a key (byte slice) to the filter
+    /// Add a key (byte slice) to the filter, growing a new stage first if
+    /// the active one is more than half full.
     pub fn insert(&mut self, key: &[u8]) {
-        let (h1, h2) = self.get_hash_pair(key);
-        let m = self.bits.len() as u64;
-
-        for i in 0..self.num_hashes {
-            // Double Hashing: g(x) = h1(x) + i * h2(x)
-            // Wrapping add simulates independent hashes without re-computing
-            let idx = h1.wrapping_add((i as u64).wrapping_mul(h2)) % m;
-            self.bits.set(idx as usize, true);
+        if self.stages.last().unwrap().fill_ratio() > GROWTH_FILL_THRESHOLD {
+            self.grow();
+        }
+        self.stages.last_mut().unwrap().insert(key);
+    }
+
+    /// Remove a key previously inserted. Decrements every stage, which is
+    /// safe even for stages the key was never in: their counters for this
+    /// key's slots are already zero (or belong to a still-live key, in
+    /// which case this is the false-negative tradeoff noted on `CountingBloom`).
+    pub fn remove(&mut self, key: &[u8]) {
+        for stage in &mut self.stages {
+            stage.remove(key);
         }
     }
 
@@ -42,27 +155,146 @@ impl BloomFilter {
     /// Returns FALSE if definitely not present.
     /// Returns TRUE if it MIGHT be present.
     pub fn contains(&self, key: &[u8]) -> bool {
-        let (h1, h2) = self.get_hash_pair(key);
-        let m = self.bits.len() as u64;
+        self.stages.iter().any(|stage| stage.contains(key))
+    }
 
-        for i in 0..self.num_hashes {
-            let idx = h1.wrapping_add((i as u64).wrapping_mul(h2)) % m;
-            if !self.bits.get(idx as usize).unwrap() {
-                return false; // Definitely not here
-            }
+    /// Fraction of the active (most recently added) stage's counters that
+    /// are nonzero. Earlier stages are omitted since they're already full
+    /// enough to have triggered growth, so the active stage is the only one
+    /// informative for capacity-planning dashboards.
+    pub fn fill_ratio(&self) -> f64 {
+        self.stages.last().unwrap().fill_ratio()
+    }
+
+    fn grow(&mut self) {
+        let stage_no = self.stages.len() as i32;
+        let next_capacity = self.base_expected_items * 2usize.saturating_pow(stage_no as u32);
+        let next_error_rate = self.base_error_rate * TIGHTENING_RATIO.powi(stage_no);
+        self.stages.push(CountingBloom::with_capacity(next_capacity, next_error_rate));
+    }
+
+    /// Serialize every stage's counters, `num_hashes`, and `m` so the
+    /// filter can be checkpointed next to a segment and rebuilt on open
+    /// instead of being reconstructed by replaying every record.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.base_expected_items.to_le_bytes());
+        out.extend_from_slice(&self.base_error_rate.to_le_bytes());
+        out.extend_from_slice(&(self.stages.len() as u32).to_le_bytes());
+
+        for stage in &self.stages {
+            out.extend_from_slice(&(stage.m as u64).to_le_bytes());
+            out.extend_from_slice(&stage.num_hashes.to_le_bytes());
+            out.extend_from_slice(&(stage.counters.len() as u32).to_le_bytes());
+            out.extend_from_slice(&stage.counters);
         }
-        true // Might be here
+        out
     }
 
-    /// Helper: Generate two independent 64-bit hashes using SeaHash
-    fn get_hash_pair(&self, key: &[u8]) -> (u64, u64) {
-        // Hash 1: Standard SeaHash
-        let h1 = hash(key);
+    pub fn deserialize(bytes: &[u8]) -> io::Result<Self> {
+        let mut r = ByteReader::new(bytes);
 
-        // Hash 2: SeaHash with a modified seed (simple XOR tweak)
-        // This provides sufficient independence for the Bloom Filter property
-        let h2 = h1.wrapping_add(0x9E3779B97F4A7C15); // Golden Ratio constant
+        let base_expected_items = r.read_usize()?;
+        let base_error_rate = r.read_f64()?;
+        let stage_count = r.read_u32()?;
 
-        (h1, h2)
+        let mut stages = Vec::with_capacity(stage_count as usize);
+        for _ in 0..stage_count {
+            let m = r.read_u64()? as usize;
+            let num_hashes = r.read_u32()?;
+            let counters = r.read_bytes()?;
+
+            // Counted once here at load by scanning every counter - after
+            // this, `insert`/`remove` keep it current incrementally.
+            let occupied = (0..m).filter(|&i| {
+                let byte = counters[i / 2];
+                (if i % 2 == 0 { byte & 0x0F } else { (byte >> 4) & 0x0F }) != 0
+            }).count();
+
+            stages.push(CountingBloom { counters, m, num_hashes, occupied });
+        }
+
+        if stages.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "bloom filter checkpoint has no stages"));
+        }
+
+        Ok(Self { stages, base_expected_items, base_error_rate })
+    }
+}
+
+/// Minimal little-endian cursor for the hand-rolled checkpoint format above
+/// (mirrors the manual framing used elsewhere in this crate, e.g. `HnswIndex::save`).
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.pos + n > self.bytes.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated bloom filter checkpoint"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn read_usize(&mut self) -> io::Result<usize> {
+        Ok(usize::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.read_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_past_zero_does_not_underflow() {
+        let mut bloom = CountingBloom::new(1024, 4);
+        let key = b"only-key";
+
+        bloom.insert(key);
+        bloom.remove(key);
+        // A second remove hits counters already at zero; `get`/`set` must
+        // clamp rather than wrap a `u8` counter past 0.
+        bloom.remove(key);
+
+        assert!(!bloom.contains(key));
+        assert_eq!(bloom.occupied, 0);
+    }
+
+    #[test]
+    fn fill_ratio_tracks_insert_and_remove() {
+        let mut bloom = CountingBloom::new(1024, 4);
+        assert_eq!(bloom.fill_ratio(), 0.0);
+
+        bloom.insert(b"a");
+        bloom.insert(b"b");
+        assert!(bloom.fill_ratio() > 0.0);
+
+        bloom.remove(b"a");
+        bloom.remove(b"b");
+        assert_eq!(bloom.fill_ratio(), 0.0);
     }
 }
@@ -0,0 +1,297 @@
+// src/graph_store.rs
+//
+// Pluggable persistence backend for `HnswIndex`'s node map, mirroring the
+// split between `storage::StorageEngine` and its `Segment`/`LsmEngine`
+// backends. `HnswIndex::save` re-serializes every node on every call, which
+// is fine for an occasional checkpoint but unusable as the backing store
+// for a graph the GC/Raft path mutates continuously - every insert or
+// remove would cost O(total_nodes). A `GraphStore` persists one node at a
+// time instead, so `HnswIndex::open_with_store` can keep a graph durable
+// without ever rewriting the whole thing.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::index::Node;
+
+/// Common interface for where `HnswIndex` keeps its nodes durable.
+/// `put_node`/`delete_node`/`flush` mutate the backend, so - same as
+/// `StorageEngine` - callers serialize access with a `Mutex`; `get_node`/
+/// `iter` only read.
+pub trait GraphStore: Send {
+    fn put_node(&mut self, id: u128, node: &Node) -> io::Result<()>;
+    fn get_node(&self, id: u128) -> io::Result<Option<Node>>;
+    fn delete_node(&mut self, id: u128) -> io::Result<()>;
+
+    /// Every node currently in the store, in backend-native order - used
+    /// once at open time to hydrate `HnswIndex`'s in-memory graph.
+    fn iter(&self) -> io::Result<Vec<(u128, Node)>>;
+
+    /// Force durable persistence of anything buffered in memory.
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Selects which `GraphStore` backend a collection is opened with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphStoreKind {
+    /// The original single-file format, kept for portability: every node
+    /// lives in an in-memory cache, and only `flush` touches disk, writing
+    /// the whole cache out at once.
+    File,
+    /// Embedded LMDB database keyed by node id. `put_node`/`delete_node`
+    /// each commit their own transaction, so persistence is incremental
+    /// and crash recovery never has to parse one big file.
+    Lmdb,
+}
+
+/// Open a fresh `GraphStore` of the given kind at `path`. `strict` mirrors
+/// `SystemProfile::strict_durability`: `GraphStoreKind::Lmdb` fsyncs after
+/// every write when set, trading latency for durability the same way
+/// `Segment::strict` does for record frames; `GraphStoreKind::File` has no
+/// equivalent knob since `flush` is the only write it ever does.
+pub fn open_graph_store(kind: GraphStoreKind, path: &Path, strict: bool) -> io::Result<Box<dyn GraphStore>> {
+    match kind {
+        GraphStoreKind::File => Ok(Box::new(FileGraphStore::open(path)?)),
+        GraphStoreKind::Lmdb => Ok(Box::new(LmdbGraphStore::open(path, strict)?)),
+    }
+}
+
+/// Encodes one node as `[id:16][is_coded:1][len:u32][codes or f32 vector][num_layers:1][layers...]`.
+/// Self-describing (the coded/uncoded length is stored inline) so decoding
+/// never needs an external `ProductQuantizer` to know how many bytes the
+/// codes occupy, unlike the single-file `HnswIndex::save` format.
+fn encode_node(node: &Node) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&node.id.to_le_bytes());
+
+    match &node.codes {
+        Some(codes) => {
+            buf.push(1);
+            buf.extend_from_slice(&(codes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(codes);
+        }
+        None => {
+            buf.push(0);
+            buf.extend_from_slice(&(node.vector.len() as u32).to_le_bytes());
+            for component in &node.vector {
+                buf.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+    }
+
+    buf.push(node.connections.len() as u8);
+    for layer in &node.connections {
+        buf.extend_from_slice(&(layer.len() as u32).to_le_bytes());
+        for conn in layer {
+            buf.extend_from_slice(&conn.to_le_bytes());
+        }
+    }
+
+    buf
+}
+
+fn decode_node(mut bytes: &[u8]) -> io::Result<Node> {
+    let mut id_buf = [0u8; 16];
+    bytes.read_exact(&mut id_buf)?;
+    let id = u128::from_le_bytes(id_buf);
+
+    let mut is_coded = [0u8; 1];
+    bytes.read_exact(&mut is_coded)?;
+
+    let mut len_buf = [0u8; 4];
+    bytes.read_exact(&mut len_buf)?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+
+    let (vector, codes) = if is_coded[0] == 1 {
+        let mut codes = vec![0u8; len];
+        bytes.read_exact(&mut codes)?;
+        (Vec::new(), Some(codes))
+    } else {
+        let mut vector = Vec::with_capacity(len);
+        let mut f32_buf = [0u8; 4];
+        for _ in 0..len {
+            bytes.read_exact(&mut f32_buf)?;
+            vector.push(f32::from_le_bytes(f32_buf));
+        }
+        (vector, None)
+    };
+
+    let mut layers_byte = [0u8; 1];
+    bytes.read_exact(&mut layers_byte)?;
+    let num_layers = layers_byte[0] as usize;
+
+    let mut connections = Vec::with_capacity(num_layers);
+    for _ in 0..num_layers {
+        let mut link_count_buf = [0u8; 4];
+        bytes.read_exact(&mut link_count_buf)?;
+        let link_count = u32::from_le_bytes(link_count_buf);
+
+        let mut links = Vec::with_capacity(link_count as usize);
+        for _ in 0..link_count {
+            let mut link_buf = [0u8; 16];
+            bytes.read_exact(&mut link_buf)?;
+            links.push(u128::from_le_bytes(link_buf));
+        }
+        connections.push(links);
+    }
+
+    Ok(Node { id, vector, codes, connections })
+}
+
+/// Portable single-file `GraphStore` backend, written as `[count:u32]` then
+/// `[len:u32][encode_node blob]` per node (its own layout, not the one
+/// `HnswIndex::save` writes): every node lives in an in-memory cache and
+/// `flush` is the only operation that touches disk, rewriting the whole
+/// file from the cache. `put_node`/`delete_node`/`get_node` never block on
+/// I/O, which is the usual tradeoff a write-back cache makes for a
+/// format with no incremental update story of its own.
+struct FileGraphStore {
+    path: PathBuf,
+    cache: HashMap<u128, Node>,
+}
+
+impl FileGraphStore {
+    fn open(path: &Path) -> io::Result<Self> {
+        let cache = if path.exists() {
+            let file = File::open(path)?;
+            let mut reader = BufReader::new(file);
+
+            let mut count_buf = [0u8; 4];
+            reader.read_exact(&mut count_buf)?;
+            let count = u32::from_le_bytes(count_buf);
+
+            let mut cache = HashMap::with_capacity(count as usize);
+            for _ in 0..count {
+                let mut len_buf = [0u8; 4];
+                reader.read_exact(&mut len_buf)?;
+                let mut bytes = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+                reader.read_exact(&mut bytes)?;
+                let node = decode_node(&bytes)?;
+                cache.insert(node.id, node);
+            }
+            cache
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self { path: path.to_path_buf(), cache })
+    }
+}
+
+impl GraphStore for FileGraphStore {
+    fn put_node(&mut self, id: u128, node: &Node) -> io::Result<()> {
+        self.cache.insert(id, node.clone());
+        Ok(())
+    }
+
+    fn get_node(&self, id: u128) -> io::Result<Option<Node>> {
+        Ok(self.cache.get(&id).cloned())
+    }
+
+    fn delete_node(&mut self, id: u128) -> io::Result<()> {
+        self.cache.remove(&id);
+        Ok(())
+    }
+
+    fn iter(&self) -> io::Result<Vec<(u128, Node)>> {
+        Ok(self.cache.iter().map(|(id, node)| (*id, node.clone())).collect())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let file = File::create(&self.path)?;
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(&(self.cache.len() as u32).to_le_bytes())?;
+        for node in self.cache.values() {
+            let encoded = encode_node(node);
+            writer.write_all(&(encoded.len() as u32).to_le_bytes())?;
+            writer.write_all(&encoded)?;
+        }
+        writer.flush()
+    }
+}
+
+fn to_io_err<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Embedded-LMDB `GraphStore`: one database keyed by the node id's
+/// big-endian bytes (so a cursor scan in `iter` comes back in id order),
+/// values the same `encode_node` blob `FileGraphStore` writes wholesale.
+/// `put_node`/`delete_node` each commit their own transaction, which is
+/// what makes persistence incremental here instead of a full rewrite.
+struct LmdbGraphStore {
+    env: heed::Env,
+    db: heed::Database<heed::types::Bytes, heed::types::Bytes>,
+    strict: bool,
+}
+
+impl LmdbGraphStore {
+    fn open(path: &Path, strict: bool) -> io::Result<Self> {
+        std::fs::create_dir_all(path)?;
+
+        // 1 GiB of address space reserved up front - LMDB only maps pages
+        // it actually touches, so this costs nothing until the graph grows
+        // into it, the same way `Segment::SEGMENT_SIZE` is a ceiling, not
+        // an allocation.
+        let env = unsafe {
+            heed::EnvOpenOptions::new()
+            .map_size(1 << 30)
+            .max_dbs(1)
+            .open(path)
+        }.map_err(to_io_err)?;
+
+        let mut wtxn = env.write_txn().map_err(to_io_err)?;
+        let db = env.create_database(&mut wtxn, Some("nodes")).map_err(to_io_err)?;
+        wtxn.commit().map_err(to_io_err)?;
+
+        Ok(Self { env, db, strict })
+    }
+}
+
+impl GraphStore for LmdbGraphStore {
+    fn put_node(&mut self, id: u128, node: &Node) -> io::Result<()> {
+        let mut wtxn = self.env.write_txn().map_err(to_io_err)?;
+        self.db.put(&mut wtxn, &id.to_be_bytes(), &encode_node(node)).map_err(to_io_err)?;
+        wtxn.commit().map_err(to_io_err)?;
+        if self.strict {
+            self.env.force_sync().map_err(to_io_err)?;
+        }
+        Ok(())
+    }
+
+    fn get_node(&self, id: u128) -> io::Result<Option<Node>> {
+        let rtxn = self.env.read_txn().map_err(to_io_err)?;
+        match self.db.get(&rtxn, &id.to_be_bytes()).map_err(to_io_err)? {
+            Some(bytes) => Ok(Some(decode_node(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn delete_node(&mut self, id: u128) -> io::Result<()> {
+        let mut wtxn = self.env.write_txn().map_err(to_io_err)?;
+        self.db.delete(&mut wtxn, &id.to_be_bytes()).map_err(to_io_err)?;
+        wtxn.commit().map_err(to_io_err)?;
+        if self.strict {
+            self.env.force_sync().map_err(to_io_err)?;
+        }
+        Ok(())
+    }
+
+    fn iter(&self) -> io::Result<Vec<(u128, Node)>> {
+        let rtxn = self.env.read_txn().map_err(to_io_err)?;
+        let mut out = Vec::new();
+        for entry in self.db.iter(&rtxn).map_err(to_io_err)? {
+            let (key, bytes) = entry.map_err(to_io_err)?;
+            let id_bytes: [u8; 16] = key.try_into()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "LMDB: malformed node key"))?;
+            out.push((u128::from_be_bytes(id_bytes), decode_node(bytes)?));
+        }
+        Ok(out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.env.force_sync().map_err(to_io_err)
+    }
+}
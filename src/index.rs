@@ -1,5 +1,5 @@
 use std::collections::{HashMap, BinaryHeap, HashSet};
-use std::sync::RwLock;
+use std::sync::{Mutex, OnceLock, RwLock};
 use std::cmp::Ordering;
 use ordered_float::OrderedFloat;
 use rand::Rng;
@@ -8,6 +8,107 @@ use std::fs::File;
 use std::path::Path;
 use crate::vector::Metric;
 use crate::model::VECTOR_DIM;
+use crate::pq::{IndexMode, ProductQuantizer};
+use crate::crypto::{self, DataKey};
+use crate::graph_store::GraphStore;
+
+/// Table-based CRC32C (Castagnoli), used by `save`/`load`/`verify` to catch
+/// bitrot the same way S3-style backends checksum each stored object - see
+/// the doc comment on `HnswIndex::save` for the on-disk layout.
+fn crc32c_table() -> &'static [u32; 256] {
+    static TABLE: OnceLock<[u32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        const POLY: u32 = 0x82F6_3B78; // CRC-32C, reflected
+        let mut table = [0u32; 256];
+        for (i, slot) in table.iter_mut().enumerate() {
+            let mut crc = i as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            }
+            *slot = crc;
+        }
+        table
+    })
+}
+
+/// Folds `data` into a running (non-finalized) CRC32C `state`, so a caller
+/// can checksum something built out of several writes - e.g. the combined
+/// trailer checksum, which is folded from every per-node checksum.
+fn crc32c_update(mut state: u32, data: &[u8]) -> u32 {
+    let table = crc32c_table();
+    for &byte in data {
+        state = table[((state ^ byte as u32) & 0xFF) as usize] ^ (state >> 8);
+    }
+    state
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    !crc32c_update(!0, data)
+}
+
+/// Tees every byte written through it into `buf`, so a node's fields can be
+/// written one at a time (matching the rest of this format) while still
+/// recovering the exact bytes to checksum once the node is done.
+struct TeeWriter<'a, W: Write> {
+    inner: &'a mut W,
+    buf: Vec<u8>,
+}
+
+impl<'a, W: Write> TeeWriter<'a, W> {
+    fn new(inner: &'a mut W) -> Self {
+        Self { inner, buf: Vec::new() }
+    }
+}
+
+impl<'a, W: Write> Write for TeeWriter<'a, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(data)?;
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Read-side mirror of `TeeWriter`: captures every byte the node-decoding
+/// loop consumes so it can be checksummed against the node's trailing
+/// CRC32C without re-encoding the node.
+struct TeeReader<'a, R: Read> {
+    inner: &'a mut R,
+    buf: Vec<u8>,
+}
+
+impl<'a, R: Read> TeeReader<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        Self { inner, buf: Vec::new() }
+    }
+}
+
+impl<'a, R: Read> Read for TeeReader<'a, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(out)?;
+        self.buf.extend_from_slice(&out[..n]);
+        Ok(n)
+    }
+}
+
+/// Outcome of `HnswIndex::verify`: a scan of a persisted index file that
+/// checks every per-node CRC32C plus the trailer, without paying to
+/// reconstruct the in-memory graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub nodes_checked: u32,
+    pub corrupt_nodes: Vec<u128>,
+    pub trailer_ok: bool,
+}
+
+impl IntegrityReport {
+    pub fn is_healthy(&self) -> bool {
+        self.corrupt_nodes.is_empty() && self.trailer_ok
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Candidate {
@@ -29,7 +130,11 @@ impl PartialOrd for Candidate {
 #[derive(Debug, Clone)]
 pub struct Node {
     pub id: u128,
+    /// Full-precision vector. Empty when `codes` holds a PQ encoding instead.
     pub vector: Vec<f32>,
+    /// PQ centroid ids, `Some` only when the index is in `IndexMode::Pq` and
+    /// a quantizer has been trained.
+    pub codes: Option<Vec<u8>>,
     pub connections: Vec<Vec<u128>>,
 }
 
@@ -40,17 +145,140 @@ pub struct HnswIndex {
     m_max: usize,
     _ef_construction: usize,
     metric: Metric,
+    mode: IndexMode,
+    quantizer: RwLock<Option<ProductQuantizer>>,
+    /// When present, every `insert`/`remove` also writes through to this
+    /// backend instead of relying solely on an occasional whole-file `save`.
+    /// `None` for the original in-memory-only graph.
+    store: Option<Mutex<Box<dyn GraphStore>>>,
 }
 
 impl HnswIndex {
     pub fn new(m_max: usize, ef: usize) -> Self {
+        Self::new_with_mode(m_max, ef, IndexMode::Exact)
+    }
+
+    /// Same as `new`, but lets the caller open the collection in
+    /// `IndexMode::Pq`. PQ mode needs a trained quantizer before it will
+    /// actually encode anything - see `train_quantizer` - so inserts made
+    /// before training still fall back to storing the full vector. Defaults
+    /// to `Metric::Cosine` - use `new_with_metric` to pick another one.
+    pub fn new_with_mode(m_max: usize, ef: usize, mode: IndexMode) -> Self {
+        Self::new_with_metric(m_max, ef, mode, Metric::Cosine)
+    }
+
+    /// Same as `new_with_mode`, but also lets the caller pick the distance
+    /// metric scored at both insert and search time (e.g. `InnerProduct`
+    /// for already-normalized embeddings) instead of always defaulting to
+    /// `Cosine`.
+    pub fn new_with_metric(m_max: usize, ef: usize, mode: IndexMode, metric: Metric) -> Self {
         Self {
             nodes: RwLock::new(HashMap::new()),
             entry_point: RwLock::new(None),
             max_layer: RwLock::new(0),
             m_max,
             _ef_construction: ef,
-            metric: Metric::Cosine,
+            metric,
+            mode,
+            quantizer: RwLock::new(None),
+            store: None,
+        }
+    }
+
+    /// Opens a graph backed by a `GraphStore` instead of the monolithic
+    /// `save`/`load` file: every node currently in `store` is read once, up
+    /// front, to hydrate the in-memory graph (crash recovery is therefore
+    /// a single `iter()` rather than a bulk file parse), and every
+    /// subsequent `insert`/`remove` writes through to `store` incrementally.
+    /// `entry_point`/`max_layer` are reconstructed with the same heuristic
+    /// `load` uses: the node with the deepest layer stack wins. Unlike
+    /// `save`/`load`, a `GraphStore` has no slot of its own for the configured
+    /// `metric` (each node block is metric-agnostic), so the caller has to
+    /// pass it back in on every open the same way it's passed at creation.
+    pub fn open_with_store(store: Box<dyn GraphStore>, m_max: usize, ef: usize, mode: IndexMode, metric: Metric) -> io::Result<Self> {
+        let mut nodes = HashMap::new();
+        let mut max_l = 0;
+        let mut ep = None;
+
+        for (id, node) in store.iter()? {
+            let num_layers = node.connections.len();
+            if num_layers > 0 {
+                let level_index = num_layers.saturating_sub(1);
+                if level_index > max_l {
+                    max_l = level_index;
+                    ep = Some(id);
+                }
+                if ep.is_none() {
+                    ep = Some(id);
+                    max_l = level_index;
+                }
+            }
+            nodes.insert(id, node);
+        }
+
+        Ok(Self {
+            nodes: RwLock::new(nodes),
+            entry_point: RwLock::new(ep),
+            max_layer: RwLock::new(max_l),
+            m_max,
+            _ef_construction: ef,
+            metric,
+            mode,
+            quantizer: RwLock::new(None),
+            store: Some(Mutex::new(store)),
+        })
+    }
+
+    /// Train the product quantizer on a sample of full-precision vectors.
+    /// No-op in `IndexMode::Exact`. Vectors inserted after this point are
+    /// stored as `m` centroid ids instead of `4*D` floats.
+    pub fn train_quantizer(&self, samples: &[Vec<f32>], m: usize) {
+        if self.mode != IndexMode::Pq {
+            return;
+        }
+        let mut quantizer = self.quantizer.write().unwrap();
+        *quantizer = Some(ProductQuantizer::train(samples, m));
+    }
+
+    /// Distance from `query` to `node`, preferring the asymmetric
+    /// table-lookup path when both a precomputed ADC `table` and the node's
+    /// PQ codes are available, and falling back to a full-precision
+    /// comparison (decoding the node's codes if needed) otherwise.
+    fn distance_to(&self, query: &[f32], table: Option<&[f32]>, node: &Node) -> f32 {
+        if let Some(codes) = &node.codes {
+            let quantizer = self.quantizer.read().unwrap();
+            let quantizer = quantizer.as_ref().expect("PQ-coded node without a trained quantizer");
+            return match table {
+                Some(table) => quantizer.asymmetric_distance(table, codes),
+                None => self.metric.distance(query, &quantizer.decode(codes)),
+            };
+        }
+        self.metric.distance(query, &node.vector)
+    }
+
+    /// Number of live nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.read().unwrap().len()
+    }
+
+    /// The distance metric this index scores candidates with - callers
+    /// doing their own scoring outside `search` (e.g. a pre-filtered scan)
+    /// need this to stay consistent with the graph instead of assuming one.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Forces durable persistence of the `GraphStore` backend this index
+    /// was opened with (see `open_with_store`) - a no-op when there isn't
+    /// one. `FileGraphStore` in particular only ever touches disk inside
+    /// `flush`, buffering every `put_node`/`delete_node` in memory the rest
+    /// of the time, so a caller on that backend has to call this (e.g. on
+    /// `ChronosDb`'s shutdown/checkpoint path) or nothing durable ever
+    /// lands on disk.
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.store {
+            Some(store) => store.lock().unwrap().flush(),
+            None => Ok(()),
         }
     }
 
@@ -69,13 +297,34 @@ impl HnswIndex {
     pub fn remove(&self, id: u128) {
         let mut nodes = self.nodes.write().unwrap();
         nodes.remove(&id);
+        if let Some(store) = &self.store {
+            let _ = store.lock().unwrap().delete_node(id);
+        }
     }
 
     pub fn insert(&self, id: u128, vector: Vec<f32>) {
         let layers = self.select_level();
+
+        // Encode once up front (if a quantizer is trained) and build the ADC
+        // table from the same encoding pass, so both the stored node and the
+        // graph-descent distance calcs below share one table.
+        let encoded = if self.mode == IndexMode::Pq {
+            self.quantizer.read().unwrap().as_ref()
+            .map(|q| (q.encode(&vector), q.build_table(&vector, self.metric)))
+        } else {
+            None
+        };
+        let table = encoded.as_ref().map(|(_, table)| table.as_slice());
+
+        let (stored_vector, stored_codes) = match &encoded {
+            Some((codes, _)) => (Vec::new(), Some(codes.clone())),
+            None => (vector.clone(), None),
+        };
+
         let mut node = Node {
             id,
-            vector: vector.clone(),
+            vector: stored_vector,
+            codes: stored_codes,
             connections: vec![vec![]; layers + 1],
         };
 
@@ -86,6 +335,9 @@ impl HnswIndex {
         if ep_guard.is_none() {
             *ep_guard = Some(id);
             *max_layer_guard = layers;
+            if let Some(store) = &self.store {
+                let _ = store.lock().unwrap().put_node(id, &node);
+            }
             nodes_guard.insert(id, node);
             return;
         }
@@ -99,8 +351,7 @@ impl HnswIndex {
             if let Some(curr_node) = nodes_guard.get(&curr_obj) {
                 if lc >= curr_node.connections.len() { break; }
 
-                let n_vec = &curr_node.vector;
-                let mut min_dist = self.metric.distance(&vector, n_vec);
+                let mut min_dist = self.distance_to(&vector, table, curr_node);
 
                 let mut changed = true;
                 while changed {
@@ -108,7 +359,7 @@ impl HnswIndex {
                     let candidates = nodes_guard[&curr_obj].connections[lc].clone();
                     for neighbor_id in candidates {
                         if let Some(neighbor) = nodes_guard.get(&neighbor_id) {
-                            let d = self.metric.distance(&vector, &neighbor.vector);
+                            let d = self.distance_to(&vector, table, neighbor);
                             if d < min_dist {
                                 min_dist = d;
                                 curr_obj = neighbor_id;
@@ -124,6 +375,7 @@ impl HnswIndex {
 
         let m_max = self.m_max;
         let curr_entry = curr_obj;
+        let mut mutated_peer = false;
 
         // Insert into all layers from 0 up to `layers`
         for lc in (0..=std::cmp::min(layers, max_layer)).rev() {
@@ -139,6 +391,7 @@ impl HnswIndex {
                         if peer.connections[lc].len() > m_max {
                             peer.connections[lc].pop();
                         }
+                        mutated_peer = true;
                     }
                 }
             }
@@ -148,6 +401,22 @@ impl HnswIndex {
             *max_layer_guard = layers;
             *ep_guard = Some(id);
         }
+        if let Some(store) = &self.store {
+            let mut store = store.lock().unwrap();
+            // The new node is the only one `nodes_guard.insert` below is
+            // about to add, but `curr_entry`'s back-edge to it (pushed into
+            // `peer.connections` above) mutated an *existing* node - if that
+            // write-through is skipped, a store like `LmdbGraphStore` that
+            // persists incrementally ends up with an asymmetric graph after
+            // a restart: the new node points at `curr_entry`, but nothing
+            // points back.
+            if mutated_peer {
+                if let Some(peer) = nodes_guard.get(&curr_entry) {
+                    let _ = store.put_node(curr_entry, peer);
+                }
+            }
+            let _ = store.put_node(id, &node);
+        }
         nodes_guard.insert(id, node);
     }
 
@@ -178,12 +447,19 @@ impl HnswIndex {
 
         let max_layer = *self.max_layer.read().unwrap();
 
+        // One ADC table for the whole search, built once from the query, so
+        // every candidate below is scored with `m` byte-indexed table adds
+        // instead of a full-precision distance computation.
+        let quantizer_guard = self.quantizer.read().unwrap();
+        let table = quantizer_guard.as_ref().map(|q| q.build_table(query, self.metric));
+        let table = table.as_deref();
+
         // 1. Zoom in from top layer
         for lc in (1..=max_layer).rev() {
             let mut changed = true;
             if let Some(node) = nodes.get(&curr_entry) {
                 if lc >= node.connections.len() { continue; }
-                let mut min_dist = self.metric.distance(query, &node.vector);
+                let mut min_dist = self.distance_to(query, table, node);
 
                 while changed {
                     changed = false;
@@ -191,7 +467,7 @@ impl HnswIndex {
                         if lc < inner_node.connections.len() {
                             for neighbor in &inner_node.connections[lc] {
                                 if let Some(n_node) = nodes.get(neighbor) {
-                                    let d = self.metric.distance(query, &n_node.vector);
+                                    let d = self.distance_to(query, table, n_node);
                                     if d < min_dist {
                                         min_dist = d;
                                         curr_entry = *neighbor;
@@ -210,7 +486,7 @@ impl HnswIndex {
         let mut visited = HashSet::new();
 
         if let Some(node) = nodes.get(&curr_entry) {
-            let d = self.metric.distance(query, &node.vector);
+            let d = self.distance_to(query, table, node);
             candidates.push(Candidate { dist: OrderedFloat(d), node_id: curr_entry });
             visited.insert(curr_entry);
 
@@ -225,7 +501,7 @@ impl HnswIndex {
                             if !visited.contains(v) {
                                 visited.insert(*v);
                                 if let Some(target) = nodes.get(v) {
-                                    let dist = self.metric.distance(query, &target.vector);
+                                    let dist = self.distance_to(query, table, target);
                                     candidates.push(Candidate { dist: OrderedFloat(dist), node_id: *v });
                                     results.push(Candidate { dist: OrderedFloat(dist), node_id: *v });
                                 }
@@ -246,33 +522,144 @@ impl HnswIndex {
         }
     }
 
+    /// Writes the graph as `[has_quantizer][quantizer?][metric][count]`, then one
+    /// `[node block][CRC32C]` per node, then a trailer of
+    /// `[count][combined CRC32C]`. The per-node checksum is a streaming
+    /// CRC32C (Castagnoli) over that node's serialized id + vector/codes +
+    /// connections; the trailer's combined checksum folds together every
+    /// per-node checksum, so `verify` can confirm the whole file without
+    /// re-deriving anything from the node blocks themselves. This mirrors
+    /// the per-object checksum scheme S3-style backends use to catch bitrot.
     pub fn save(&self, path: &Path) -> io::Result<()> {
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
         let nodes = self.nodes.read().unwrap();
 
+        // The quantizer codebook travels in the same file as the graph it
+        // quantizes, right before the node count, so a collection opened in
+        // PQ mode never needs a separate retraining pass after restart.
+        let quantizer = self.quantizer.read().unwrap();
+        writer.write_all(&[if quantizer.is_some() { 1u8 } else { 0u8 }])?;
+        if let Some(q) = quantizer.as_ref() {
+            q.save(&mut writer)?;
+        }
+
+        // Persisted right after the quantizer flag/blob so a reload doesn't
+        // have to guess which metric scored this graph's edges - see
+        // `Metric::to_byte`.
+        writer.write_all(&[self.metric.to_byte()])?;
+
         writer.write_all(&(nodes.len() as u32).to_le_bytes())?;
 
+        let mut combined = !0u32;
+
         for (_, node) in nodes.iter() {
-            writer.write_all(&node.id.to_le_bytes())?;
-            for val in &node.vector {
-                writer.write_all(&val.to_le_bytes())?;
+            let mut tee = TeeWriter::new(&mut writer);
+            tee.write_all(&node.id.to_le_bytes())?;
+            tee.write_all(&[if node.codes.is_some() { 1u8 } else { 0u8 }])?;
+            if let Some(codes) = &node.codes {
+                tee.write_all(codes)?;
+            } else {
+                for val in &node.vector {
+                    tee.write_all(&val.to_le_bytes())?;
+                }
             }
-            writer.write_all(&(node.connections.len() as u8).to_le_bytes())?;
+            tee.write_all(&(node.connections.len() as u8).to_le_bytes())?;
             for layer in &node.connections {
-                writer.write_all(&(layer.len() as u32).to_le_bytes())?;
+                tee.write_all(&(layer.len() as u32).to_le_bytes())?;
                 for conn in layer {
-                    writer.write_all(&conn.to_le_bytes())?;
+                    tee.write_all(&conn.to_le_bytes())?;
                 }
             }
+
+            let node_crc = crc32c(&tee.buf).to_le_bytes();
+            writer.write_all(&node_crc)?;
+            combined = crc32c_update(combined, &node_crc);
         }
+
+        writer.write_all(&(nodes.len() as u32).to_le_bytes())?;
+        writer.write_all(&(!combined).to_le_bytes())?;
         Ok(())
     }
 
+    /// Decodes one node's fields (tee'd so they can be checksummed) plus its
+    /// trailing CRC32C, shared by `load` (which needs the decoded `Node`)
+    /// and `verify` (which only needs to know whether it checked out).
+    /// Returns the node, whether its checksum matched, and the raw checksum
+    /// bytes so the caller can fold them into the running trailer checksum.
+    fn decode_node<R: Read>(
+        reader: &mut R,
+        quantizer: Option<&ProductQuantizer>,
+    ) -> io::Result<(Node, bool, [u8; 4])> {
+        let mut tee = TeeReader::new(reader);
+
+        let mut id_buf = [0u8; 16];
+        tee.read_exact(&mut id_buf)?;
+        let id = u128::from_le_bytes(id_buf);
+
+        let mut is_coded = [0u8; 1];
+        tee.read_exact(&mut is_coded)?;
+
+        let (vector, codes) = if is_coded[0] == 1 {
+            let m = quantizer.expect("coded node in an index file with no quantizer").m();
+            let mut codes = vec![0u8; m];
+            tee.read_exact(&mut codes)?;
+            (Vec::new(), Some(codes))
+        } else {
+            let mut vector = Vec::with_capacity(VECTOR_DIM);
+            let mut f32_buf = [0u8; 4];
+            for _ in 0..VECTOR_DIM {
+                tee.read_exact(&mut f32_buf)?;
+                vector.push(f32::from_le_bytes(f32_buf));
+            }
+            (vector, None)
+        };
+
+        let mut layers_byte = [0u8; 1];
+        tee.read_exact(&mut layers_byte)?;
+        let num_layers = layers_byte[0] as usize;
+
+        let mut connections = Vec::with_capacity(num_layers);
+        for _ in 0..num_layers {
+            let mut link_count_buf = [0u8; 4];
+            tee.read_exact(&mut link_count_buf)?;
+            let link_count = u32::from_le_bytes(link_count_buf);
+
+            let mut links = Vec::with_capacity(link_count as usize);
+            for _ in 0..link_count {
+                let mut link_buf = [0u8; 16];
+                tee.read_exact(&mut link_buf)?;
+                links.push(u128::from_le_bytes(link_buf));
+            }
+            connections.push(links);
+        }
+
+        let ok = crc32c(&tee.buf).to_le_bytes();
+        let reader = tee.inner;
+        let mut crc_buf = [0u8; 4];
+        reader.read_exact(&mut crc_buf)?;
+        let matched = ok == crc_buf;
+
+        Ok((Node { id, vector, codes, connections }, matched, crc_buf))
+    }
+
     pub fn load(path: &Path, m_max: usize, ef: usize) -> io::Result<Self> {
         let file = File::open(path)?;
         let mut reader = BufReader::new(file);
 
+        let mut has_quantizer = [0u8; 1];
+        reader.read_exact(&mut has_quantizer)?;
+        let quantizer = if has_quantizer[0] == 1 {
+            Some(ProductQuantizer::load(&mut reader)?)
+        } else {
+            None
+        };
+        let mode = if quantizer.is_some() { IndexMode::Pq } else { IndexMode::Exact };
+
+        let mut metric_buf = [0u8; 1];
+        reader.read_exact(&mut metric_buf)?;
+        let metric = Metric::from_byte(metric_buf[0])?;
+
         let mut count_buf = [0u8; 4];
         reader.read_exact(&mut count_buf)?;
         let count = u32::from_le_bytes(count_buf);
@@ -280,23 +667,275 @@ impl HnswIndex {
         let mut nodes = HashMap::new();
         let mut max_l = 0;
         let mut ep = None;
+        let mut combined = !0u32;
 
         for _ in 0..count {
-            let mut id_buf = [0u8; 16];
-            reader.read_exact(&mut id_buf)?;
-            let id = u128::from_le_bytes(id_buf);
+            let (node, matched, crc_bytes) = Self::decode_node(&mut reader, quantizer.as_ref())?;
+            if !matched {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("HnswIndex: checksum mismatch for node {}", node.id),
+                ));
+            }
+            combined = crc32c_update(combined, &crc_bytes);
+
+            let id = node.id;
+            let num_layers = node.connections.len();
+            if num_layers > 0 {
+                let level_index = num_layers.saturating_sub(1);
+                if level_index > max_l {
+                    max_l = level_index;
+                    ep = Some(id);
+                }
+                if ep.is_none() {
+                    ep = Some(id);
+                    max_l = level_index;
+                }
+            }
+
+            nodes.insert(id, node);
+        }
+
+        let mut trailer_count_buf = [0u8; 4];
+        reader.read_exact(&mut trailer_count_buf)?;
+        let mut trailer_crc_buf = [0u8; 4];
+        reader.read_exact(&mut trailer_crc_buf)?;
+        if u32::from_le_bytes(trailer_count_buf) != count || (!combined).to_le_bytes() != trailer_crc_buf {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "HnswIndex: trailer checksum mismatch"));
+        }
+
+        Ok(Self {
+            nodes: RwLock::new(nodes),
+            entry_point: RwLock::new(ep),
+            max_layer: RwLock::new(max_l),
+            m_max,
+            _ef_construction: ef,
+            metric,
+            mode,
+            quantizer: RwLock::new(quantizer),
+            store: None,
+        })
+    }
+
+    /// Scans a persisted index file validating every per-node CRC32C and
+    /// the trailer, without building the in-memory `nodes` map - cheap
+    /// enough for the GC thread to proactively call between compactions.
+    pub fn verify(path: &Path) -> io::Result<IntegrityReport> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+
+        let mut has_quantizer = [0u8; 1];
+        reader.read_exact(&mut has_quantizer)?;
+        let quantizer = if has_quantizer[0] == 1 {
+            Some(ProductQuantizer::load(&mut reader)?)
+        } else {
+            None
+        };
+
+        let mut metric_buf = [0u8; 1];
+        reader.read_exact(&mut metric_buf)?;
+        Metric::from_byte(metric_buf[0])?;
+
+        let mut count_buf = [0u8; 4];
+        reader.read_exact(&mut count_buf)?;
+        let count = u32::from_le_bytes(count_buf);
+
+        let mut corrupt_nodes = Vec::new();
+        let mut combined = !0u32;
+
+        for _ in 0..count {
+            let (node, matched, crc_bytes) = Self::decode_node(&mut reader, quantizer.as_ref())?;
+            if !matched {
+                corrupt_nodes.push(node.id);
+            }
+            combined = crc32c_update(combined, &crc_bytes);
+        }
+
+        let mut trailer_count_buf = [0u8; 4];
+        reader.read_exact(&mut trailer_count_buf)?;
+        let mut trailer_crc_buf = [0u8; 4];
+        reader.read_exact(&mut trailer_crc_buf)?;
+        let trailer_ok = u32::from_le_bytes(trailer_count_buf) == count
+            && (!combined).to_le_bytes() == trailer_crc_buf;
+
+        Ok(IntegrityReport { nodes_checked: count, corrupt_nodes, trailer_ok })
+    }
+
+    /// Serializes one node's id + vector/codes + connections into a flat
+    /// buffer - the same fields `save` writes inline, but collected up
+    /// front here so `save_encrypted` has a single plaintext blob to seal.
+    fn encode_node_block(node: &Node) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&node.id.to_le_bytes());
+        buf.push(if node.codes.is_some() { 1u8 } else { 0u8 });
+        if let Some(codes) = &node.codes {
+            buf.extend_from_slice(codes);
+        } else {
+            for val in &node.vector {
+                buf.extend_from_slice(&val.to_le_bytes());
+            }
+        }
+        buf.push(node.connections.len() as u8);
+        for layer in &node.connections {
+            buf.extend_from_slice(&(layer.len() as u32).to_le_bytes());
+            for conn in layer {
+                buf.extend_from_slice(&conn.to_le_bytes());
+            }
+        }
+        buf
+    }
 
+    /// Inverse of `encode_node_block`, parsed from an already-decrypted
+    /// buffer rather than streamed off a reader (the sealed framing means
+    /// `load_encrypted` always has the whole plaintext block in hand before
+    /// it needs to parse any of it).
+    fn decode_node_from_block(mut block: &[u8], quantizer: Option<&ProductQuantizer>) -> io::Result<Node> {
+        let mut id_buf = [0u8; 16];
+        block.read_exact(&mut id_buf)?;
+        let id = u128::from_le_bytes(id_buf);
+
+        let mut is_coded = [0u8; 1];
+        block.read_exact(&mut is_coded)?;
+
+        let (vector, codes) = if is_coded[0] == 1 {
+            let m = quantizer.expect("coded node in an index file with no quantizer").m();
+            let mut codes = vec![0u8; m];
+            block.read_exact(&mut codes)?;
+            (Vec::new(), Some(codes))
+        } else {
             let mut vector = Vec::with_capacity(VECTOR_DIM);
             let mut f32_buf = [0u8; 4];
             for _ in 0..VECTOR_DIM {
-                reader.read_exact(&mut f32_buf)?;
+                block.read_exact(&mut f32_buf)?;
                 vector.push(f32::from_le_bytes(f32_buf));
             }
+            (vector, None)
+        };
+
+        let mut layers_byte = [0u8; 1];
+        block.read_exact(&mut layers_byte)?;
+        let num_layers = layers_byte[0] as usize;
+
+        let mut connections = Vec::with_capacity(num_layers);
+        for _ in 0..num_layers {
+            let mut link_count_buf = [0u8; 4];
+            block.read_exact(&mut link_count_buf)?;
+            let link_count = u32::from_le_bytes(link_count_buf);
+
+            let mut links = Vec::with_capacity(link_count as usize);
+            for _ in 0..link_count {
+                let mut link_buf = [0u8; 16];
+                block.read_exact(&mut link_buf)?;
+                links.push(u128::from_le_bytes(link_buf));
+            }
+            connections.push(links);
+        }
+
+        Ok(Node { id, vector, codes, connections })
+    }
+
+    /// Same on-disk shape as `save` up through the quantizer blob and metric
+    /// byte, but then
+    /// writes `[MAGIC][VERSION][salt]` and seals every node block (and a
+    /// trailing `[count]` record) under an XChaCha20-Poly1305 key derived
+    /// from `master_key` via HKDF, each under its own random nonce. Each
+    /// sealed block is length-prefixed (`[len][nonce‖ciphertext‖tag]`),
+    /// since ciphertext length varies and isn't otherwise inferable the way
+    /// the plaintext format infers it structurally.
+    pub fn save_encrypted(&self, path: &Path, master_key: &[u8]) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+        let nodes = self.nodes.read().unwrap();
+
+        let quantizer = self.quantizer.read().unwrap();
+        writer.write_all(&[if quantizer.is_some() { 1u8 } else { 0u8 }])?;
+        if let Some(q) = quantizer.as_ref() {
+            q.save(&mut writer)?;
+        }
+
+        // Same spot as the plaintext `save` - right after the quantizer,
+        // before the encryption header - so a reload doesn't have to guess
+        // which metric scored this graph's edges. See `Metric::to_byte`.
+        writer.write_all(&[self.metric.to_byte()])?;
+
+        let salt = crypto::random_salt();
+        let key = DataKey::derive(master_key, &salt);
+        writer.write_all(crypto::MAGIC)?;
+        writer.write_all(&[crypto::VERSION])?;
+        writer.write_all(&salt)?;
+
+        writer.write_all(&(nodes.len() as u32).to_le_bytes())?;
+
+        for (_, node) in nodes.iter() {
+            let block = Self::encode_node_block(node);
+            let sealed = crypto::seal(&key, &block);
+            writer.write_all(&(sealed.len() as u32).to_le_bytes())?;
+            writer.write_all(&sealed)?;
+        }
 
-            let mut layers_byte = [0u8; 1];
-            reader.read_exact(&mut layers_byte)?;
-            let num_layers = layers_byte[0] as usize;
+        let trailer = crypto::seal(&key, &(nodes.len() as u32).to_le_bytes());
+        writer.write_all(&(trailer.len() as u32).to_le_bytes())?;
+        writer.write_all(&trailer)?;
+        Ok(())
+    }
 
+    /// Inverse of `save_encrypted`. Fails with an `io::Error` - cleanly,
+    /// never panicking - if `master_key` is wrong, any sealed block was
+    /// tampered with, or the file isn't in the encrypted format at all
+    /// (bad magic/version), so a caller can tell "wrong key" apart from
+    /// "this just isn't an encrypted file" and fall back to `load`.
+    pub fn load_encrypted(path: &Path, master_key: &[u8], m_max: usize, ef: usize) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+
+        let mut has_quantizer = [0u8; 1];
+        reader.read_exact(&mut has_quantizer)?;
+        let quantizer = if has_quantizer[0] == 1 {
+            Some(ProductQuantizer::load(&mut reader)?)
+        } else {
+            None
+        };
+        let mode = if quantizer.is_some() { IndexMode::Pq } else { IndexMode::Exact };
+
+        let mut metric_buf = [0u8; 1];
+        reader.read_exact(&mut metric_buf)?;
+        let metric = Metric::from_byte(metric_buf[0])?;
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != crypto::MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an encrypted HnswIndex file (bad magic)"));
+        }
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != crypto::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported encrypted HnswIndex version {}", version[0]),
+            ));
+        }
+        let mut salt = [0u8; crypto::SALT_LEN];
+        reader.read_exact(&mut salt)?;
+        let key = DataKey::derive(master_key, &salt);
+
+        let mut count_buf = [0u8; 4];
+        reader.read_exact(&mut count_buf)?;
+        let count = u32::from_le_bytes(count_buf);
+
+        let mut nodes = HashMap::new();
+        let mut max_l = 0;
+        let mut ep = None;
+
+        for _ in 0..count {
+            let mut len_buf = [0u8; 4];
+            reader.read_exact(&mut len_buf)?;
+            let mut sealed = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            reader.read_exact(&mut sealed)?;
+            let block = crypto::open(&key, &sealed)?;
+            let node = Self::decode_node_from_block(&block, quantizer.as_ref())?;
+
+            let id = node.id;
+            let num_layers = node.connections.len();
             if num_layers > 0 {
                 let level_index = num_layers.saturating_sub(1);
                 if level_index > max_l {
@@ -309,31 +948,59 @@ impl HnswIndex {
                 }
             }
 
-            let mut connections = Vec::with_capacity(num_layers);
-            for _ in 0..num_layers {
-                let mut link_count_buf = [0u8; 4];
-                reader.read_exact(&mut link_count_buf)?;
-                let link_count = u32::from_le_bytes(link_count_buf);
-
-                let mut links = Vec::with_capacity(link_count as usize);
-                for _ in 0..link_count {
-                    let mut link_buf = [0u8; 16];
-                    reader.read_exact(&mut link_buf)?;
-                    links.push(u128::from_le_bytes(link_buf));
-                }
-                connections.push(links);
-            }
-            let node = Node { id, vector, connections };
             nodes.insert(id, node);
         }
 
+        let mut trailer_len_buf = [0u8; 4];
+        reader.read_exact(&mut trailer_len_buf)?;
+        let mut trailer_sealed = vec![0u8; u32::from_le_bytes(trailer_len_buf) as usize];
+        reader.read_exact(&mut trailer_sealed)?;
+        let trailer = crypto::open(&key, &trailer_sealed)?;
+        if trailer.len() != 4 || u32::from_le_bytes(trailer.try_into().unwrap()) != count {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "encrypted HnswIndex: trailer count mismatch"));
+        }
+
         Ok(Self {
             nodes: RwLock::new(nodes),
-           entry_point: RwLock::new(ep),
-           max_layer: RwLock::new(max_l),
-           m_max,
-           _ef_construction: ef,
-           metric: Metric::Cosine,
+            entry_point: RwLock::new(ep),
+            max_layer: RwLock::new(max_l),
+            m_max,
+            _ef_construction: ef,
+            metric,
+            mode,
+            quantizer: RwLock::new(quantizer),
+            store: None,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_store::{open_graph_store, GraphStoreKind};
+
+    #[test]
+    fn insert_persists_the_back_edge_on_the_existing_peer() {
+        let path = std::env::temp_dir().join(format!("chronos-hnsw-backedge-{}", uuid::Uuid::new_v4()));
+
+        {
+            let store = open_graph_store(GraphStoreKind::File, &path, false).unwrap();
+            let index = HnswIndex::open_with_store(store, 16, 100, IndexMode::Exact, Metric::Cosine).unwrap();
+            index.insert(1, vec![0.0; VECTOR_DIM]);
+            index.insert(2, vec![1.0; VECTOR_DIM]);
+            // `FileGraphStore` only writes on `flush` - without this the
+            // reopen below would just see an empty file regardless of
+            // whether the back-edge fix works.
+            index.flush().unwrap();
+        }
+
+        let store = open_graph_store(GraphStoreKind::File, &path, false).unwrap();
+        let peer = store.get_node(1).unwrap().expect("node 1 must have been persisted");
+        assert!(
+            peer.connections.iter().any(|layer| layer.contains(&2)),
+            "node 1's back-edge to node 2 must survive a reopen, not just node 2's forward edge to node 1"
+        );
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -2,128 +2,526 @@ pub mod model;
 pub mod storage;
 pub mod vector;
 pub mod index;
+pub mod graph_store;
+pub mod pq;
 pub mod server;
 pub mod parser;
 pub mod filter;
 pub mod cluster;
 pub mod manager;
+pub mod metrics;
+pub mod crypto;
+pub mod merkle;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Mutex, RwLock};
 use std::fmt;
 use std::fs;
+use dashmap::DashMap;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 use crate::model::Record;
-use crate::storage::Segment;
+use crate::storage::{self, EngineKind, StorageEngine};
 use crate::index::HnswIndex;
+use crate::graph_store::{self, GraphStoreKind};
 use crate::filter::BloomFilter;
+use crate::pq::IndexMode;
+use crate::vector::Metric;
+use crate::parser::Filter;
+use crate::metrics::Metrics;
 
 use rkyv::ser::{serializers::AllocSerializer, Serializer};
 use rkyv::Deserialize;
 
+/// Number of buffered events a `watch` subscriber can fall behind by before
+/// it starts missing notifications (it'll see a `RecvError::Lagged` and
+/// should just re-issue the watch).
+const WATCH_CHANNEL_CAPACITY: usize = 16;
+
+/// Records are partitioned across this many independent shards by
+/// `id % NUM_SHARDS`, each with its own segment file, offset index, and
+/// Bloom filter, so unrelated keys never serialize on the same lock - see
+/// `Shard`.
+const NUM_SHARDS: usize = 16;
+
+/// Number of records grouped into one framed block by `ChronosDb::snapshot`.
+/// Caps how many `Record`s are materialized and `rkyv`-encoded (or decoded,
+/// on the restore side) at once, so a snapshot of a multi-million-vector
+/// database is built and installed a bounded chunk at a time instead of one
+/// giant in-memory blob.
+const SNAPSHOT_CHUNK_RECORDS: usize = 4096;
+
+/// One framed block inside a buffer produced by `ChronosDb::snapshot`:
+/// `record_count` records, `rkyv`-encoded into `bytes`.
+pub struct SnapshotChunk<'a> {
+    pub record_count: usize,
+    pub bytes: &'a [u8],
+}
+
+/// Walks a buffer produced by `ChronosDb::snapshot` and yields each framed
+/// chunk in turn, borrowing straight out of `data` rather than copying it.
+pub fn snapshot_chunks(data: &[u8]) -> impl Iterator<Item = SnapshotChunk<'_>> {
+    let mut cursor = 0usize;
+    std::iter::from_fn(move || {
+        if cursor >= data.len() { return None; }
+
+        let record_count = u32::from_le_bytes(data[cursor..cursor + 4].try_into().unwrap()) as usize;
+        cursor += 4;
+        let byte_len = u32::from_le_bytes(data[cursor..cursor + 4].try_into().unwrap()) as usize;
+        cursor += 4;
+        let bytes = &data[cursor..cursor + byte_len];
+        cursor += byte_len;
+
+        Some(SnapshotChunk { record_count, bytes })
+    })
+}
+
+/// Pushed to `watch` subscribers of a key when a committed mutation lands.
+#[derive(Clone, Debug)]
+pub enum ChangeEvent {
+    Updated(Vec<u8>),
+    Deleted,
+}
+
+/// One independent partition of the keyspace: its own storage engine,
+/// offset index, and Bloom filter, all guarded separately so that writes to
+/// one shard never block reads or writes to another. `ChronosDb` holds
+/// `NUM_SHARDS` of these, selected by `shard_index`.
+struct Shard {
+    engine: Mutex<Box<dyn StorageEngine>>,
+    index: RwLock<HashMap<u128, Vec<u64>>>,
+    bloom_filter: RwLock<BloomFilter>,
+    path: std::path::PathBuf,
+}
+
+impl Shard {
+    fn open(path: std::path::PathBuf, engine_kind: EngineKind, strict: bool, expected_items: usize, master_key: Option<&[u8]>) -> std::io::Result<Self> {
+        let engine = storage::open_engine_with_key(engine_kind, &path, strict, master_key)?;
+
+        let bloom_filter = fs::read(with_suffix(&path, ".bloom"))
+        .ok()
+        .and_then(|bytes| BloomFilter::deserialize(&bytes).ok())
+        .unwrap_or_else(|| BloomFilter::new(expected_items, 0.01));
+
+        Ok(Self {
+            engine: Mutex::new(engine),
+            index: RwLock::new(HashMap::new()),
+            bloom_filter: RwLock::new(bloom_filter),
+            path,
+        })
+    }
+
+    fn read_record(&self, offset: u64) -> Option<Record> {
+        let engine = self.engine.lock().ok()?;
+        engine.read(offset).ok()
+    }
+}
+
+fn shard_index(id: u128) -> usize {
+    (id % NUM_SHARDS as u128) as usize
+}
+
+/// Appends `suffix` to `path`'s full file name, rather than using
+/// `with_extension` (which would instead replace whatever follows the last
+/// `.`) - needed so a per-shard path like `wal.dat.shard3` can still grow a
+/// `.bloom`/`.compacted` suffix of its own without clobbering the shard
+/// number.
+fn with_suffix(path: &std::path::Path, suffix: &str) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    std::path::PathBuf::from(name)
+}
+
+fn shard_path(storage_path: &std::path::Path, shard: usize) -> std::path::PathBuf {
+    with_suffix(storage_path, &format!(".shard{}", shard))
+}
+
 pub struct ChronosDb {
-    active_segment: Mutex<Segment>,
-    pub index: RwLock<HashMap<u128, Vec<u64>>>,
+    shards: Vec<Shard>,
+    storage_path: std::path::PathBuf,
+    engine_kind: EngineKind,
+    /// Secondary range index: opaque caller-supplied sort key -> record id,
+    /// ordered so `range_scan` can walk `[start, end)` without a full scan.
+    /// Records inserted without a sort key (the empty `Vec`) are never
+    /// added, so they simply don't participate in range queries. Shared
+    /// across shards rather than partitioned, since it's keyed by sort key
+    /// instead of id.
+    order_index: RwLock<BTreeMap<Vec<u8>, u128>>,
     pub vector_index: HnswIndex,
-    pub bloom_filter: RwLock<BloomFilter>,
+    /// Per-key broadcast channels for `watch` subscribers. Entries are
+    /// created lazily on first subscribe and live for the life of the
+    /// process - an idle key with no subscribers just sits as an empty
+    /// channel, which is cheap enough not to bother reaping.
+    notifiers: DashMap<u128, broadcast::Sender<ChangeEvent>>,
+    /// Request counters, Raft write outcomes, and last-compaction stats for
+    /// the Prometheus scrape endpoint (see `metrics::start_metrics_api`).
+    pub metrics: Metrics,
+    /// When set, every shard's `Segment` seals its record frames under a
+    /// key derived from this master key (see `crypto`), and `compact`
+    /// re-opens rewritten segments the same way so Copy-GC doesn't
+    /// accidentally decrypt data at rest.
+    encryption_key: Option<Vec<u8>>,
 }
 
 impl fmt::Debug for ChronosDb {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let index_count: usize = self.shards.iter()
+        .map(|s| s.index.read().map(|idx| idx.len()).unwrap_or(0))
+        .sum();
         f.debug_struct("ChronosDb")
-        .field("index_count", &self.index.read().unwrap().len())
+        .field("index_count", &index_count)
         .finish()
     }
 }
 
 impl ChronosDb {
     pub fn new(storage_path: &std::path::Path, index_path: &std::path::Path, strict_durability: bool) -> Self {
-        let segment = Segment::new(storage_path, strict_durability).expect("Failed to initialize storage");
-        let idx = HashMap::new();
+        Self::new_with_engine(storage_path, index_path, strict_durability, EngineKind::Segment)
+    }
+
+    /// Same as `new`, but lets the caller pick the storage backend. Use
+    /// `EngineKind::Lsm` for write-heavy, high-cardinality update workloads
+    /// that would otherwise grow the append-only segment without bound.
+    pub fn new_with_engine(storage_path: &std::path::Path, index_path: &std::path::Path, strict_durability: bool, engine_kind: EngineKind) -> Self {
+        Self::new_with_options(storage_path, index_path, strict_durability, engine_kind, IndexMode::Exact)
+    }
+
+    /// Same as `new_with_engine`, but also lets the caller open the vector
+    /// index in `IndexMode::Pq` instead of the default exact mode. PQ mode
+    /// trades a small amount of recall for far less memory per vector; see
+    /// `HnswIndex::train_quantizer`.
+    pub fn new_with_options(storage_path: &std::path::Path, index_path: &std::path::Path, strict_durability: bool, engine_kind: EngineKind, vector_mode: IndexMode) -> Self {
+        Self::new_with_metric(storage_path, index_path, strict_durability, engine_kind, vector_mode, Metric::Cosine)
+    }
 
-        let vector_index = if index_path.exists() {
+    /// Same as `new_with_options`, but also lets the caller pick the
+    /// distance `Metric` the vector index scores edges with - `Metric::Cosine`
+    /// is the default assumed by every shorter constructor above. Persisted
+    /// alongside the graph itself (see `HnswIndex::save`), so a collection
+    /// opened in `Metric::InnerProduct` stays in that mode across restarts.
+    pub fn new_with_metric(
+        storage_path: &std::path::Path,
+        index_path: &std::path::Path,
+        strict_durability: bool,
+        engine_kind: EngineKind,
+        vector_mode: IndexMode,
+        vector_metric: Metric,
+    ) -> Self {
+        Self::new_with_encryption(storage_path, index_path, strict_durability, engine_kind, vector_mode, vector_metric, None)
+    }
+
+    /// Same as `new_with_metric`, but with an optional master key that
+    /// turns on AEAD encryption at rest (see `crypto`): every shard's
+    /// `Segment` seals its record frames under a key derived from it, and
+    /// an existing whole-file HNSW graph is read through
+    /// `HnswIndex::load_encrypted` instead of `load`. `None` keeps the
+    /// original plaintext formats, so existing databases still open.
+    pub fn new_with_encryption(
+        storage_path: &std::path::Path,
+        index_path: &std::path::Path,
+        strict_durability: bool,
+        engine_kind: EngineKind,
+        vector_mode: IndexMode,
+        vector_metric: Metric,
+        master_key: Option<Vec<u8>>,
+    ) -> Self {
+        Self::new_with_graph_store(storage_path, index_path, strict_durability, engine_kind, vector_mode, vector_metric, master_key, None)
+    }
+
+    /// Same as `new_with_encryption`, but lets the caller back the HNSW
+    /// graph with an incremental `GraphStore` (see `graph_store`) instead of
+    /// the monolithic `save`/`load` file. `Some(kind)` opens (or creates)
+    /// the store at `index_path` and hydrates the graph from it via
+    /// `HnswIndex::open_with_store`; `None` keeps the whole-file format, so
+    /// existing databases still open unchanged.
+    pub fn new_with_graph_store(
+        storage_path: &std::path::Path,
+        index_path: &std::path::Path,
+        strict_durability: bool,
+        engine_kind: EngineKind,
+        vector_mode: IndexMode,
+        vector_metric: Metric,
+        master_key: Option<Vec<u8>>,
+        graph_store_kind: Option<GraphStoreKind>,
+    ) -> Self {
+        // Each shard expects roughly 1/NUM_SHARDS of the keyspace, so its
+        // Bloom filter is sized down to match instead of every shard
+        // reserving capacity for the whole database.
+        let expected_items_per_shard = 1_000_000 / NUM_SHARDS;
+        let shards = (0..NUM_SHARDS)
+        .map(|i| Shard::open(shard_path(storage_path, i), engine_kind, strict_durability, expected_items_per_shard, master_key.as_deref())
+             .expect("Failed to initialize storage shard"))
+        .collect();
+
+        let vector_index = if let Some(kind) = graph_store_kind {
+            println!("Opening HNSW Graph through a {:?} GraphStore...", kind);
+            let store = graph_store::open_graph_store(kind, index_path, strict_durability)
+                .expect("Failed to open graph store");
+            HnswIndex::open_with_store(store, 16, 100, vector_mode, vector_metric)
+                .expect("Failed to hydrate HNSW graph from store")
+        } else if index_path.exists() {
             println!("Loading HNSW Graph from disk...");
-            HnswIndex::load(index_path, 16, 100).unwrap_or_else(|e| {
-                println!("Failed to load graph: {}, creating new.", e);
-                HnswIndex::new(16, 100)
-            })
+            match &master_key {
+                Some(key) => HnswIndex::load_encrypted(index_path, key, 16, 100).unwrap_or_else(|e| {
+                    println!("Failed to load encrypted graph: {}, creating new.", e);
+                    HnswIndex::new_with_metric(16, 100, vector_mode, vector_metric)
+                }),
+                None => HnswIndex::load(index_path, 16, 100).unwrap_or_else(|e| {
+                    println!("Failed to load graph: {}, creating new.", e);
+                    HnswIndex::new_with_metric(16, 100, vector_mode, vector_metric)
+                }),
+            }
         } else {
-            HnswIndex::new(16, 100)
+            HnswIndex::new_with_metric(16, 100, vector_mode, vector_metric)
         };
 
-        let bloom_filter = RwLock::new(BloomFilter::new(1_000_000, 0.01));
-
         Self {
-            active_segment: Mutex::new(segment),
-            index: RwLock::new(idx),
+            shards,
+            storage_path: storage_path.to_path_buf(),
+            engine_kind,
+            order_index: RwLock::new(BTreeMap::new()),
             vector_index,
-            bloom_filter,
+            notifiers: DashMap::new(),
+            metrics: Metrics::new(),
+            encryption_key: master_key,
+        }
+    }
+
+    /// Bytes currently on disk across every shard, used by the metrics
+    /// endpoint's `chronos_segment_bytes` gauge.
+    pub fn storage_bytes(&self) -> u64 {
+        self.shards.iter()
+        .map(|s| fs::metadata(&s.path).map(|m| m.len()).unwrap_or(0))
+        .sum()
+    }
+
+    /// Average Bloom filter fill ratio across every shard, used by the
+    /// metrics endpoint's `chronos_bloom_filter_fill_ratio` gauge.
+    pub fn bloom_fill_ratio(&self) -> f64 {
+        let ratios: Vec<f64> = self.shards.iter()
+        .filter_map(|s| s.bloom_filter.read().ok().map(|bf| bf.fill_ratio()))
+        .collect();
+        if ratios.is_empty() {
+            return 0.0;
+        }
+        ratios.iter().sum::<f64>() / ratios.len() as f64
+    }
+
+    /// Subscribes to future changes for `id`, creating its broadcast
+    /// channel on first use. The same channel is shared by every
+    /// subscriber of that key, so a slow subscriber can lag and miss
+    /// events (see `WATCH_CHANNEL_CAPACITY`) without affecting the others.
+    pub fn watch(&self, id: Uuid) -> broadcast::Receiver<ChangeEvent> {
+        self.notifiers
+        .entry(id.as_u128())
+        .or_insert_with(|| broadcast::channel(WATCH_CHANNEL_CAPACITY).0)
+        .subscribe()
+    }
+
+    /// Pushes `event` to every current subscriber of `id`. A no-op (aside
+    /// from the lookup) when nobody is watching that key.
+    fn notify_change(&self, id: u128, event: ChangeEvent) {
+        if let Some(sender) = self.notifiers.get(&id) {
+            let _ = sender.send(event);
         }
     }
 
     pub fn insert(&self, record: Record) -> Result<(), String> {
         let id = record.key;
         let vector_clone = record.vector.clone();
+        let shard = &self.shards[shard_index(id)];
 
         let offset = {
-            let mut segment = self.active_segment.lock().map_err(|_| "Poisoned Lock")?;
-            segment.append(&record).map_err(|e| e.to_string())?
+            let mut engine = shard.engine.lock().map_err(|_| "Poisoned Lock")?;
+            engine.append(&record).map_err(|e| e.to_string())?
         };
 
         {
-            let mut bf = self.bloom_filter.write().map_err(|_| "Poisoned Lock")?;
+            let mut bf = shard.bloom_filter.write().map_err(|_| "Poisoned Lock")?;
             bf.insert(&id.to_le_bytes());
         }
 
         {
-            let mut idx = self.index.write().map_err(|_| "Poisoned Lock")?;
+            let mut idx = shard.index.write().map_err(|_| "Poisoned Lock")?;
             idx.entry(id).or_insert_with(Vec::new).push(offset);
         }
 
+        if !record.sort_key.is_empty() {
+            let mut order_idx = self.order_index.write().map_err(|_| "Poisoned Lock")?;
+            order_idx.insert(record.sort_key.clone(), id);
+        }
+
         self.vector_index.insert(id, vector_clone);
+        self.notify_change(id, ChangeEvent::Updated(record.payload));
 
         Ok(())
     }
 
+    /// Same as `insert`, but for a whole group of records at once. Records
+    /// are grouped by destination shard first, so each shard's engine,
+    /// bloom filter, and index locks are taken only once for however many
+    /// records in the batch land in that shard, instead of once per record
+    /// overall - what makes `OP_BATCH` cheaper than N individual inserts.
+    /// The vector index and `order_index` have no batch entry point of
+    /// their own, so they're still updated one record at a time after the
+    /// per-shard locks above are released.
+    pub fn insert_many(&self, records: Vec<Record>) -> Vec<Result<(), String>> {
+        // Remember each record's original position so results can be
+        // returned in submission order despite being processed grouped by
+        // shard.
+        let mut by_shard: Vec<Vec<(usize, &Record)>> = vec![Vec::new(); NUM_SHARDS];
+        for (i, record) in records.iter().enumerate() {
+            by_shard[shard_index(record.key)].push((i, record));
+        }
+
+        let mut results: Vec<Option<Result<(), String>>> = (0..records.len()).map(|_| None).collect();
+
+        for (shard_idx, items) in by_shard.into_iter().enumerate() {
+            if items.is_empty() {
+                continue;
+            }
+            let shard = &self.shards[shard_idx];
+
+            let mut offsets: Vec<Result<u64, String>> = Vec::with_capacity(items.len());
+            {
+                let mut engine = match shard.engine.lock() {
+                    Ok(g) => g,
+                    Err(_) => {
+                        for (i, _) in &items {
+                            results[*i] = Some(Err("Poisoned Lock".to_string()));
+                        }
+                        continue;
+                    }
+                };
+                for (_, record) in &items {
+                    offsets.push(engine.append(record).map_err(|e| e.to_string()));
+                }
+            }
+
+            let mut bf = match shard.bloom_filter.write() {
+                Ok(g) => g,
+                Err(_) => {
+                    for (i, _) in &items {
+                        results[*i] = Some(Err("Poisoned Lock".to_string()));
+                    }
+                    continue;
+                }
+            };
+            let mut idx = match shard.index.write() {
+                Ok(g) => g,
+                Err(_) => {
+                    for (i, _) in &items {
+                        results[*i] = Some(Err("Poisoned Lock".to_string()));
+                    }
+                    continue;
+                }
+            };
+
+            for ((i, record), offset) in items.iter().zip(offsets.into_iter()) {
+                match offset {
+                    Ok(offset) => {
+                        bf.insert(&record.key.to_le_bytes());
+                        idx.entry(record.key).or_insert_with(Vec::new).push(offset);
+                        results[*i] = Some(Ok(()));
+                    }
+                    Err(e) => results[*i] = Some(Err(e)),
+                }
+            }
+        }
+
+        {
+            let mut order_idx = self.order_index.write().unwrap();
+            for (record, result) in records.iter().zip(results.iter()) {
+                if !record.sort_key.is_empty() && matches!(result, Some(Ok(()))) {
+                    order_idx.insert(record.sort_key.clone(), record.key);
+                }
+            }
+        }
+
+        for (record, result) in records.iter().zip(results.iter()) {
+            if matches!(result, Some(Ok(()))) {
+                self.vector_index.insert(record.key, record.vector.clone());
+                self.notify_change(record.key, ChangeEvent::Updated(record.payload.clone()));
+            }
+        }
+
+        results.into_iter().map(|r| r.unwrap_or_else(|| Err("Poisoned Lock".to_string()))).collect()
+    }
+
     pub fn delete(&self, id: Uuid) -> Result<(), String> {
+        let id_val = id.as_u128();
+        let shard = &self.shards[shard_index(id_val)];
+
+        // Double-checked read-then-upgrade: a read lock first to see
+        // whether this shard even knows about `id`, so idempotent or
+        // retried deletes of an already-gone (or never-existent) key skip
+        // the write lock - and the sort-key lookup below - entirely.
+        let present = shard.index.read().map_err(|_| "Poisoned Lock")?.contains_key(&id_val);
+        if !present {
+            return Ok(());
+        }
+
+        // Fetched before the index/bloom entries are torn down, purely to
+        // learn the sort key (if any) so the matching `order_index` entry
+        // can be dropped too.
+        let sort_key = self.get_latest(id).map(|r| r.sort_key).filter(|k| !k.is_empty());
+
         {
-            let mut idx = self.index.write().map_err(|_| "Poisoned Lock")?;
-            idx.remove(&id.as_u128());
+            let mut idx = shard.index.write().map_err(|_| "Poisoned Lock")?;
+            // Re-check under the write lock: a racing delete may have
+            // already removed this id between the read check above and
+            // acquiring the write lock.
+            if idx.remove(&id_val).is_none() {
+                return Ok(());
+            }
         }
-        self.vector_index.remove(id.as_u128());
+        {
+            let mut bf = shard.bloom_filter.write().map_err(|_| "Poisoned Lock")?;
+            bf.remove(&id_val.to_le_bytes());
+        }
+        if let Some(sort_key) = sort_key {
+            let mut order_idx = self.order_index.write().map_err(|_| "Poisoned Lock")?;
+            order_idx.remove(&sort_key);
+        }
+        self.vector_index.remove(id_val);
+        self.notify_change(id_val, ChangeEvent::Deleted);
         Ok(())
     }
 
     pub fn get_latest(&self, id: Uuid) -> Option<Record> {
+        let shard = &self.shards[shard_index(id.as_u128())];
+
         {
-            let bf = self.bloom_filter.read().ok()?;
+            let bf = shard.bloom_filter.read().ok()?;
             if !bf.contains(&id.as_u128().to_le_bytes()) {
                 return None;
             }
         }
 
         let offset = {
-            let idx = self.index.read().ok()?;
+            let idx = shard.index.read().ok()?;
             *idx.get(&id.as_u128())?.last()?
         };
-        self.read_record(offset)
+        shard.read_record(offset)
     }
 
     pub fn get_as_of(&self, id: Uuid, target_time: u64) -> Option<Record> {
+        let shard = &self.shards[shard_index(id.as_u128())];
+
         {
-            let bf = self.bloom_filter.read().ok()?;
+            let bf = shard.bloom_filter.read().ok()?;
             if !bf.contains(&id.as_u128().to_le_bytes()) {
                 return None;
             }
         }
 
         let offsets = {
-            let idx = self.index.read().ok()?;
+            let idx = shard.index.read().ok()?;
             idx.get(&id.as_u128())?.clone()
         };
 
         for offset in offsets.iter().rev() {
-            if let Some(record) = self.read_record(*offset) {
+            if let Some(record) = shard.read_record(*offset) {
                 if record.valid_time.start <= target_time && target_time < record.valid_time.end {
                     return Some(record);
                 }
@@ -133,8 +531,10 @@ impl ChronosDb {
     }
 
     pub fn get_history(&self, id: Uuid) -> Vec<Record> {
+        let shard = &self.shards[shard_index(id.as_u128())];
+
         {
-            if let Ok(bf) = self.bloom_filter.read() {
+            if let Ok(bf) = shard.bloom_filter.read() {
                 if !bf.contains(&id.as_u128().to_le_bytes()) {
                     return vec![];
                 }
@@ -142,7 +542,7 @@ impl ChronosDb {
         }
 
         let offsets = {
-            let idx = self.index.read().ok();
+            let idx = shard.index.read().ok();
             if idx.is_none() { return vec![]; }
             match idx.unwrap().get(&id.as_u128()) {
                 Some(list) => list.clone(),
@@ -152,126 +552,382 @@ impl ChronosDb {
 
         let mut history = Vec::new();
         for offset in offsets {
-            if let Some(record) = self.read_record(offset) {
+            if let Some(record) = shard.read_record(offset) {
                 history.push(record);
             }
         }
         history
     }
 
-    fn read_record(&self, offset: u64) -> Option<Record> {
-        let segment = self.active_segment.lock().ok()?;
-        segment.read(offset).ok()
-    }
+    /// Vector search restricted to records whose payload matches `filter`.
+    /// There's no payload index yet, so this pre-filters by scanning every
+    /// known id's latest record (across every shard) and only then scores
+    /// the survivors against the query - a linear scan instead of the HNSW
+    /// graph walk, but it still skips the (much more expensive) vector
+    /// comparison for ids the filter rules out. Falls back to the normal
+    /// ANN search when there's no filter to apply.
+    pub fn filtered_vector_search(&self, query: &[f32], filter: Option<&Filter>, k: usize) -> Vec<(u128, f32)> {
+        let filter = match filter {
+            Some(f) => f,
+            None => return self.vector_index.search(query, k),
+        };
 
-    // --- SNAPSHOTS ---
+        let metric = self.vector_index.metric();
 
-    pub fn snapshot(&self) -> Result<Vec<u8>, std::io::Error> {
-        let index = self.index.read().unwrap();
-        let mut records = Vec::new();
-        for (_key, offsets) in index.iter() {
-            if let Some(last_offset) = offsets.last() {
-                if let Some(record) = self.read_record(*last_offset) {
-                    records.push(record);
-                }
+        let ids: Vec<u128> = self.shards.iter()
+        .flat_map(|s| match s.index.read() {
+            Ok(idx) => idx.keys().copied().collect::<Vec<_>>(),
+            Err(_) => vec![],
+        })
+        .collect();
+
+        let mut scored: Vec<(u128, f32)> = ids.into_iter()
+        .filter_map(|id| {
+            let record = self.get_latest(Uuid::from_u128(id))?;
+            if !filter.matches(&record.payload) {
+                return None;
             }
-        }
+            Some((id, metric.distance(query, &record.vector)))
+        })
+        .collect();
 
-        println!("[SNAPSHOT] Serializing {} records (Binary/rkyv)...", records.len());
+        scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(k);
+        scored
+    }
 
-        let mut serializer = AllocSerializer::<4096>::default();
-        serializer.serialize_value(&records)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    /// Walks the secondary range index for keys in `[start, end)`, fetching
+    /// each matching id's latest record. Stops after `limit` items and, if
+    /// more keys remain past that point, returns the first key not returned
+    /// as a continuation token - callers resume by passing it back as `start`
+    /// (`start` is inclusive, so the next page begins exactly at that key and
+    /// no record is skipped or returned twice).
+    pub fn range_scan(&self, start: &[u8], end: &[u8], limit: usize) -> (Vec<(u128, Vec<u8>)>, Option<Vec<u8>>) {
+        let ids: Vec<(Vec<u8>, u128)> = match self.order_index.read() {
+            Ok(idx) => idx.range(start.to_vec()..end.to_vec())
+                .take(limit.saturating_add(1))
+                .map(|(k, v)| (k.clone(), *v))
+                .collect(),
+            Err(_) => return (vec![], None),
+        };
+
+        let continuation = if ids.len() > limit {
+            ids.get(limit).map(|(k, _)| k.clone())
+        } else {
+            None
+        };
 
-        let bytes = serializer.into_serializer().into_inner();
-        Ok(bytes.into_vec())
+        let results = ids.into_iter()
+            .take(limit)
+            .filter_map(|(_, id)| {
+                let record = self.get_latest(Uuid::from_u128(id))?;
+                Some((id, record.payload))
+            })
+            .collect();
+
+        (results, continuation)
     }
 
-    pub fn restore(&self, snapshot_data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-        println!("[RESTORE] Reading binary snapshot ({} bytes)...", snapshot_data.len());
+    // --- SNAPSHOTS ---
 
-        let mut aligned = rkyv::AlignedVec::with_capacity(snapshot_data.len());
-        aligned.extend_from_slice(snapshot_data);
+    /// Writes one framed chunk (`[record_count: u32 LE][byte_len: u32 LE][rkyv bytes]`)
+    /// into `out` and empties `chunk`. A no-op on an empty chunk, so callers
+    /// can unconditionally flush a trailing partial chunk after their loop.
+    fn flush_snapshot_chunk(chunk: &mut Vec<Record>, out: &mut Vec<u8>) -> Result<(), std::io::Error> {
+        if chunk.is_empty() { return Ok(()); }
 
-        let archived = unsafe { rkyv::archived_root::<Vec<Record>>(&aligned) };
-        let records: Vec<Record> = archived.deserialize(&mut rkyv::Infallible).unwrap();
+        let mut serializer = AllocSerializer::<4096>::default();
+        serializer.serialize_value(chunk)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        let bytes = serializer.into_serializer().into_inner().into_vec();
 
-        let count = records.len();
-        println!("[RESTORE] Hydrating {} records...", count);
+        out.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(&bytes);
+        chunk.clear();
+        Ok(())
+    }
 
-        {
-            let mut idx = self.index.write().unwrap();
-            idx.clear();
+    /// Serializes the latest version of every live record as a sequence of
+    /// framed chunks (see `flush_snapshot_chunk`) of at most
+    /// `SNAPSHOT_CHUNK_RECORDS` records each, instead of one `rkyv` envelope
+    /// for the whole database. Bounds how many `Record`s are held in memory
+    /// at once while building the snapshot, which matters once the database
+    /// holds millions of vectors.
+    pub fn snapshot(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut out = Vec::new();
+        let mut chunk: Vec<Record> = Vec::with_capacity(SNAPSHOT_CHUNK_RECORDS);
+        let mut total = 0usize;
+
+        for shard in &self.shards {
+            let index = shard.index.read().unwrap();
+            for (_key, offsets) in index.iter() {
+                if let Some(last_offset) = offsets.last() {
+                    if let Some(record) = shard.read_record(*last_offset) {
+                        chunk.push(record);
+                        total += 1;
+                        if chunk.len() >= SNAPSHOT_CHUNK_RECORDS {
+                            Self::flush_snapshot_chunk(&mut chunk, &mut out)?;
+                        }
+                    }
+                }
+            }
+        }
+        Self::flush_snapshot_chunk(&mut chunk, &mut out)?;
+
+        println!("[SNAPSHOT] Serialized {} records in chunks of up to {} (Binary/rkyv)...", total, SNAPSHOT_CHUNK_RECORDS);
+        Ok(out)
+    }
+
+    /// Clears every index and filter ahead of a restore. Split out of
+    /// `restore` so `install_snapshot` can reset once and then feed chunks
+    /// in via `restore_chunk` as they're read off the wire, instead of
+    /// buffering the whole snapshot before touching any state.
+    pub fn restore_reset(&self) {
+        let expected_items_per_shard = 1_000_000 / NUM_SHARDS;
+        for shard in &self.shards {
+            shard.index.write().unwrap().clear();
+            let mut bf = shard.bloom_filter.write().unwrap();
+            *bf = BloomFilter::new(expected_items_per_shard, 0.01);
         }
         {
-            let mut bf = self.bloom_filter.write().unwrap();
-            *bf = BloomFilter::new(1_000_000, 0.01);
+            let mut order_idx = self.order_index.write().unwrap();
+            order_idx.clear();
         }
         self.vector_index.clear();
+    }
+
+    /// Decodes one `snapshot()`-framed chunk's `rkyv` bytes and inserts its
+    /// records. Only that chunk's records are ever materialized at once, so
+    /// a caller looping over `snapshot_chunks(data)` keeps peak memory
+    /// bounded by the chunk size rather than the total snapshot size.
+    pub fn restore_chunk(&self, chunk_bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut aligned = rkyv::AlignedVec::with_capacity(chunk_bytes.len());
+        aligned.extend_from_slice(chunk_bytes);
+
+        let archived = unsafe { rkyv::archived_root::<Vec<Record>>(&aligned) };
+        let records: Vec<Record> = archived.deserialize(&mut rkyv::Infallible).unwrap();
 
         for record in records {
             self.insert(record).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
         }
+        Ok(())
+    }
+
+    /// One-shot convenience over `restore_reset`/`restore_chunk` for callers
+    /// that already hold the whole chunked snapshot buffer in memory.
+    /// Prefer feeding chunks in directly (as `install_snapshot` does) when
+    /// the buffer is too large to want duplicated in memory.
+    pub fn restore(&self, snapshot_data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        println!("[RESTORE] Reading chunked snapshot ({} bytes)...", snapshot_data.len());
+
+        self.restore_reset();
+
+        let mut total = 0usize;
+        for chunk in snapshot_chunks(snapshot_data) {
+            self.restore_chunk(chunk.bytes)?;
+            total += chunk.record_count;
+        }
 
-        println!("[RESTORE] Success.");
+        println!("[RESTORE] Hydrated {} records.", total);
         Ok(())
     }
 
-    pub fn compact(&self, history_limit: usize) -> Result<(), Box<dyn std::error::Error>> {
+    /// Forces durable persistence of the vector index's `GraphStore`
+    /// backend (see `HnswIndex::flush`). A no-op when the collection wasn't
+    /// opened with one; the whole-file `save`/`load` format is written only
+    /// by an explicit `HnswIndex::save`, which this does not call. The
+    /// `GraphStoreKind::Lmdb` backend already commits every write, but
+    /// `GraphStoreKind::File` only touches disk here, so callers
+    /// on the shutdown path must call this before exiting or its graph
+    /// never reaches disk at all.
+    pub fn checkpoint(&self) -> std::io::Result<()> {
+        self.vector_index.flush()
+    }
+
+    /// Runs Copy-GC one shard at a time, so only that shard is
+    /// stopped-the-world while the rest of the database keeps serving
+    /// reads and writes, and returns the total bytes reclaimed across all
+    /// shards.
+    pub fn compact(&self, history_limit: usize) -> Result<u64, Box<dyn std::error::Error>> {
         println!("[GC] Starting Compaction (Retention: Last {} versions)...", history_limit);
 
-        // 1. GLOBAL LOCK (Stop-the-World)
-        let mut index_lock = self.index.write().map_err(|_| "Poisoned Index Lock")?;
-        let mut segment_lock = self.active_segment.lock().map_err(|_| "Poisoned Segment Lock")?;
+        let mut total_moved = 0u64;
+        let mut total_dropped = 0u64;
+        let mut total_reclaimed = 0u64;
 
-        // 2. Prepare New Segment
-        let old_path = segment_lock.file_path.clone();
-        let new_path = old_path.with_extension("compacted");
+        for shard in &self.shards {
+            let (moved, dropped, reclaimed) = self.compact_shard(shard, history_limit)?;
+            total_moved += moved;
+            total_dropped += dropped;
+            total_reclaimed += reclaimed;
+        }
 
-        let mut new_segment = Segment::new(&new_path, true)?;
+        self.metrics.record_compaction(total_moved, total_dropped, total_reclaimed);
 
-        // 3. Iterate & Copy Live Data
-        let mut new_index_map: HashMap<u128, Vec<u64>> = HashMap::new();
-        let mut moved_count = 0;
-        let mut dropped_count = 0;
+        println!("[GC] Compaction Complete.");
+        println!("     - Moved (Live): {}", total_moved);
+        println!("     - Dropped (Dead/Old): {}", total_dropped);
+        println!("     - Reclaimed: {} bytes", total_reclaimed);
 
-        for (key, offsets) in index_lock.iter() {
-            // Retention Policy
-            let start_idx = if offsets.len() > history_limit {
-                dropped_count += offsets.len() - history_limit;
-                offsets.len() - history_limit
-            } else {
-                0
-            };
+        Ok(total_reclaimed)
+    }
+
+    /// Compacts a single shard: rewrites its live records into a fresh
+    /// segment (or, for the LSM backend, just reapplies the retention
+    /// policy to its index) and returns `(moved, dropped, reclaimed_bytes)`.
+    fn compact_shard(&self, shard: &Shard, history_limit: usize) -> Result<(u64, u64, u64), Box<dyn std::error::Error>> {
+        // 1. SHARD LOCK (Stop-the-World for this shard only)
+        let mut index_lock = shard.index.write().map_err(|_| "Poisoned Index Lock")?;
+        let mut engine_lock = shard.engine.lock().map_err(|_| "Poisoned Engine Lock")?;
+
+        let mut moved_count = 0u64;
+        let mut dropped_count = 0u64;
+        let mut reclaimed_bytes = 0u64;
+
+        match self.engine_kind {
+            // Copy-GC: rewrite every live record into a fresh segment file,
+            // then atomically swap it in for the old one.
+            EngineKind::Segment => {
+                let old_len = fs::metadata(&shard.path).map(|m| m.len()).unwrap_or(0);
+                let new_path = with_suffix(&shard.path, ".compacted");
+                let mut new_engine = storage::open_engine_with_key(EngineKind::Segment, &new_path, true, self.encryption_key.as_deref())?;
+
+                let mut new_index_map: HashMap<u128, Vec<u64>> = HashMap::new();
+
+                for (key, offsets) in index_lock.iter() {
+                    let start_idx = if offsets.len() > history_limit {
+                        dropped_count += (offsets.len() - history_limit) as u64;
+                        offsets.len() - history_limit
+                    } else {
+                        0
+                    };
+
+                    for &old_offset in &offsets[..start_idx] {
+                        let _ = engine_lock.release_payload(old_offset);
+                    }
+
+                    let offsets_to_keep = &offsets[start_idx..];
+                    let mut new_offsets = Vec::new();
+
+                    for &old_offset in offsets_to_keep {
+                        if let Ok(record) = engine_lock.read(old_offset) {
+                            let new_offset = new_engine.append(&record)?;
+                            new_offsets.push(new_offset);
+                            moved_count += 1;
+                        }
+                    }
+                    new_index_map.insert(*key, new_offsets);
+                }
 
-            let offsets_to_keep = &offsets[start_idx..];
-            let mut new_offsets = Vec::new();
+                *index_lock = new_index_map;
+                *engine_lock = new_engine;
+
+                if fs::remove_file(&shard.path).is_ok() {
+                    fs::rename(&new_path, &shard.path)?;
+                    // The chunk log travels with its segment, so the reopen
+                    // below loads only the chunks the surviving records use.
+                    fs::rename(with_suffix(&new_path, ".chunks"), with_suffix(&shard.path, ".chunks"))?;
+                    // Re-open at the original shard path to maintain a
+                    // consistent file handle.
+                    *engine_lock = storage::open_engine_with_key(EngineKind::Segment, &shard.path, true, self.encryption_key.as_deref())?;
+                }
 
-            for &old_offset in offsets_to_keep {
-                if let Ok(record) = segment_lock.read(old_offset) {
-                    let new_offset = new_segment.append(&record)?;
-                    new_offsets.push(new_offset);
-                    moved_count += 1;
+                let new_len = fs::metadata(&shard.path).map(|m| m.len()).unwrap_or(0);
+                reclaimed_bytes = old_len.saturating_sub(new_len);
+            }
+
+            // The LSM backend compacts its own SSTables in the background,
+            // so Copy-GC here just applies the retention policy to the
+            // index; the superseded keys become garbage that sled reclaims
+            // on its own schedule.
+            EngineKind::Lsm => {
+                let mut new_index_map: HashMap<u128, Vec<u64>> = HashMap::new();
+
+                for (key, offsets) in index_lock.iter() {
+                    let start_idx = if offsets.len() > history_limit {
+                        dropped_count += (offsets.len() - history_limit) as u64;
+                        offsets.len() - history_limit
+                    } else {
+                        0
+                    };
+
+                    new_index_map.insert(*key, offsets[start_idx..].to_vec());
+                    moved_count += (offsets.len() - start_idx) as u64;
                 }
+
+                *index_lock = new_index_map;
+                engine_lock.flush()?;
             }
-            new_index_map.insert(*key, new_offsets);
         }
 
-        // 4. Atomic Swap
-        *index_lock = new_index_map;
-        *segment_lock = new_segment;
+        drop(index_lock);
+        drop(engine_lock);
 
-        // 5. Cleanup Disk
-        if fs::remove_file(&old_path).is_ok() {
-            fs::rename(&new_path, &old_path)?;
-            // Re-open at original path to maintain consistent file handle
-            *segment_lock = Segment::new(&old_path, true)?;
+        if let Ok(bf) = shard.bloom_filter.read() {
+            let _ = fs::write(with_suffix(&shard.path, ".bloom"), bf.serialize());
         }
 
-        println!("[GC] Compaction Complete.");
-        println!("     - Moved (Live): {}", moved_count);
-        println!("     - Dropped (Dead/Old): {}", dropped_count);
+        Ok((moved_count, dropped_count, reclaimed_bytes))
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::VECTOR_DIM;
+
+    struct TempDb {
+        db: ChronosDb,
+        storage_path: std::path::PathBuf,
+        index_path: std::path::PathBuf,
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for i in 0..NUM_SHARDS {
+                let shard = shard_path(&self.storage_path, i);
+                fs::remove_file(&shard).ok();
+                fs::remove_file(with_suffix(&shard, ".bloom")).ok();
+            }
+            fs::remove_file(&self.index_path).ok();
+        }
+    }
+
+    fn open_temp_db(name: &str) -> TempDb {
+        let storage_path = std::env::temp_dir().join(format!("chronos-lib-test-{}-{}.wal", name, Uuid::new_v4()));
+        let index_path = std::env::temp_dir().join(format!("chronos-lib-test-{}-{}.idx", name, Uuid::new_v4()));
+        let db = ChronosDb::new(&storage_path, &index_path, false);
+        TempDb { db, storage_path, index_path }
+    }
+
+    #[test]
+    fn range_scan_pagination_token_resumes_without_gaps_or_dupes() {
+        let temp = open_temp_db("range-scan");
+
+        for i in 0..10u8 {
+            let sort_key = vec![i];
+            let record = Record::new(Uuid::new_v4(), vec![0.0; VECTOR_DIM], vec![i], sort_key, i as u64);
+            temp.db.insert(record).unwrap();
+        }
+
+        let (first_page, token) = temp.db.range_scan(&[0u8], &[10u8], 4);
+        assert_eq!(first_page.len(), 4);
+        let token = token.expect("more keys remain past the first page");
+
+        let (second_page, token2) = temp.db.range_scan(&token, &[10u8], 4);
+        assert_eq!(second_page.len(), 4);
+        let token2 = token2.expect("more keys remain past the second page");
+
+        let (third_page, token3) = temp.db.range_scan(&token2, &[10u8], 4);
+        assert_eq!(third_page.len(), 2);
+        assert!(token3.is_none(), "no keys remain past the last page");
+
+        let mut seen: Vec<u8> = [&first_page, &second_page, &third_page]
+            .iter()
+            .flat_map(|page| page.iter().map(|(_, payload)| payload[0]))
+            .collect();
+        seen.sort();
+        assert_eq!(seen, (0..10u8).collect::<Vec<_>>(), "pagination must cover every record exactly once");
     }
 }
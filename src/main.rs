@@ -7,6 +7,8 @@ use chronos::cluster::network::ChronosNetwork;
 use chronos::cluster::api::start_raft_api;
 use chronos::server::ChronosServer;
 use chronos::manager::{self, SystemProfile};
+use chronos::metrics::start_metrics_api;
+use chronos::merkle::start_merkle_api;
 use openraft::{Config, Raft, SnapshotPolicy};
 use openraft::storage::Adaptor;
 
@@ -21,6 +23,12 @@ struct Args {
 
     #[clap(long, default_value = "20001")]
     raft_port: u16,
+
+    #[clap(long, default_value = "9100")]
+    metrics_port: u16,
+
+    #[clap(long, default_value = "9200")]
+    merkle_port: u16,
 }
 
 fn main() {
@@ -55,9 +63,11 @@ async fn async_main(profile: SystemProfile) {
 
     let wal_file = format!("node_{}_wal.dat", args.node_id);
     let index_file = format!("node_{}_index.dat", args.node_id);
+    let raft_dir = format!("node_{}_raft", args.node_id);
 
     let storage_path = Path::new(&wal_file);
     let index_path = Path::new(&index_file);
+    let raft_path = Path::new(&raft_dir);
 
     println!("Initializing Storage Engine...");
     let db = Arc::new(ChronosDb::new(storage_path, index_path, profile.strict_durability));
@@ -75,7 +85,8 @@ async fn async_main(profile: SystemProfile) {
         ..Default::default()
     };
 
-    let store = ChronosStore::new(db.clone());
+    println!("Loading Raft metadata store...");
+    let store = ChronosStore::new(db.clone(), raft_path);
     let network = ChronosNetwork::new();
 
     let (log_store, state_machine) = Adaptor::new(store.clone());
@@ -97,6 +108,20 @@ async fn async_main(profile: SystemProfile) {
     });
     println!("Raft HTTP API listening on port {}", raft_port);
 
+    let metrics_db = db.clone();
+    let metrics_port = args.metrics_port;
+    tokio::spawn(async move {
+        start_metrics_api(metrics_db, metrics_port).await;
+    });
+    println!("Prometheus metrics listening on port {}", metrics_port);
+
+    let merkle_db = db.clone();
+    let merkle_port = args.merkle_port;
+    tokio::spawn(async move {
+        start_merkle_api(merkle_db, merkle_port).await;
+    });
+    println!("Merkle anti-entropy API listening on port {}", merkle_port);
+
     let addr = args.addr.clone();
     let db_clone = db.clone();
     let raft_clone = raft.clone();
@@ -111,4 +136,7 @@ async fn async_main(profile: SystemProfile) {
 
     tokio::signal::ctrl_c().await.unwrap();
     println!("Shutting down.");
+    if let Err(e) = db.checkpoint() {
+        eprintln!("Failed to flush vector index on shutdown: {}", e);
+    }
 }
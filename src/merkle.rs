@@ -0,0 +1,271 @@
+// src/merkle.rs
+//
+// Anti-entropy support: a Merkle tree over the key space so two replicas
+// can confirm they hold identical data - or localize exactly which key
+// ranges diverged - without streaming the whole dataset at each other.
+// Like `metrics.rs`'s scrape endpoint, the tree is never persisted; it's
+// recomputed on demand from each shard's live index, which is cheap enough
+// for an operation that only runs after a partition heals.
+
+use std::sync::Arc;
+use warp::Filter;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::ChronosDb;
+
+/// Every 4 bits of key (one hex nibble) is a level of the tree, so each
+/// node has 16 children - shallow enough that a divergent range is
+/// localized in a handful of round-trips, wide enough that a healthy
+/// comparison only costs a couple of requests.
+const FANOUT: u32 = 16;
+const NIBBLE_BITS: u32 = 4;
+const MAX_PREFIX_BITS: u32 = 128;
+
+/// One (key, tx_time, blake3(payload), vector_hash) leaf, hashed together so
+/// any field diverging between replicas changes the leaf hash.
+fn leaf_hash(key: u128, tx_time: u64, payload: &[u8], vector: &[f32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&key.to_le_bytes());
+    hasher.update(&tx_time.to_le_bytes());
+    hasher.update(blake3::hash(payload).as_bytes());
+    let mut vector_hasher = blake3::Hasher::new();
+    for component in vector {
+        vector_hasher.update(&component.to_le_bytes());
+    }
+    hasher.update(vector_hasher.finalize().as_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+/// Combines a node's children into its own hash. Children are fed in a
+/// fixed order (nibble value, ascending) so two replicas with the same
+/// leaves always arrive at the same hash regardless of insertion order.
+fn node_hash(children: &[[u8; 32]]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    for child in children {
+        hasher.update(child);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// True when `key`'s top `prefix_bits` bits equal `prefix` (which holds
+/// those same top bits, zero-padded below).
+fn key_matches_prefix(key: u128, prefix_bits: u32, prefix: u128) -> bool {
+    if prefix_bits == 0 {
+        return true;
+    }
+    let shift = 128 - prefix_bits;
+    (key >> shift) == (prefix >> shift)
+}
+
+/// Hashes every leaf under `prefix` (`prefix_bits` bits deep) by recursing
+/// one nibble at a time down to `MAX_PREFIX_BITS`, where a "node" is just
+/// the single leaf whose key fully determines the prefix. `leaves` is
+/// unsorted; matches are re-collected at each level, which is the
+/// recompute-on-demand tradeoff this module takes instead of maintaining a
+/// tree incrementally.
+fn hash_range(leaves: &[(u128, [u8; 32])], prefix_bits: u32, prefix: u128) -> [u8; 32] {
+    let matching: Vec<&(u128, [u8; 32])> = leaves.iter()
+        .filter(|(key, _)| key_matches_prefix(*key, prefix_bits, prefix))
+        .collect();
+
+    if matching.is_empty() {
+        return [0u8; 32];
+    }
+
+    if prefix_bits >= MAX_PREFIX_BITS {
+        return matching[0].1;
+    }
+
+    let children: Vec<[u8; 32]> = (0..FANOUT)
+        .map(|nibble| {
+            let child_bits = prefix_bits + NIBBLE_BITS;
+            let child_prefix = prefix | ((nibble as u128) << (128 - child_bits));
+            hash_range(leaves, child_bits, child_prefix)
+        })
+        .collect();
+
+    node_hash(&children)
+}
+
+/// One child of a `/merkle-children` response: its nibble (0..16) and the
+/// hash of everything under `prefix` extended by that nibble. An all-zero
+/// hash means that sub-range currently holds no records.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleChild {
+    pub nibble: u8,
+    pub hash: [u8; 32],
+}
+
+impl ChronosDb {
+    /// Every id currently present in any shard's offset index, paired with
+    /// its leaf hash - the input to both `merkle_root` and
+    /// `merkle_children`.
+    fn merkle_leaves(&self) -> Vec<(u128, [u8; 32])> {
+        self.shards.iter()
+        .flat_map(|shard| match shard.index.read() {
+            Ok(idx) => idx.keys().copied().collect::<Vec<_>>(),
+            Err(_) => vec![],
+        })
+        .filter_map(|id| {
+            let record = self.get_latest(Uuid::from_u128(id))?;
+            Some((id, leaf_hash(id, record.tx_time, &record.payload, &record.vector)))
+        })
+        .collect()
+    }
+
+    /// Hash of the entire key space - two replicas with an identical root
+    /// hold identical data; this is the cheap check to run before
+    /// descending any further.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        let leaves = self.merkle_leaves();
+        hash_range(&leaves, 0, 0)
+    }
+
+    /// The 16 child hashes one nibble below `prefix` (`prefix_bits` bits
+    /// deep) - the unit of descent a repair walk requests for every prefix
+    /// whose hash disagreed with the peer's, until it bottoms out at
+    /// individual divergent keys.
+    pub fn merkle_children(&self, prefix_bits: u32, prefix: u128) -> Vec<MerkleChild> {
+        let leaves = self.merkle_leaves();
+        (0..FANOUT)
+        .map(|nibble| {
+            let child_bits = prefix_bits + NIBBLE_BITS;
+            let child_prefix = prefix | ((nibble as u128) << (128 - child_bits));
+            MerkleChild { nibble: nibble as u8, hash: hash_range(&leaves, child_bits, child_prefix) }
+        })
+        .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct ChildrenQuery {
+    /// Hex-encoded prefix, as many nibbles as `prefix_bits / 4` - e.g. `"a3"`
+    /// for an 8-bit prefix. Empty string (or omitted) asks for the root's
+    /// immediate children.
+    #[serde(default)]
+    prefix: String,
+}
+
+fn parse_prefix(hex: &str) -> Result<(u32, u128), String> {
+    let bits = hex.len() as u32 * NIBBLE_BITS;
+    if bits > MAX_PREFIX_BITS {
+        return Err("prefix longer than 32 hex digits".to_string());
+    }
+
+    // Accumulate into the low bits first, then left-align into the top
+    // `bits` of the u128 - `key_matches_prefix`/`hash_range` compare
+    // against the top bits of `prefix`, regardless of how many are in use.
+    let mut low_bits_value: u128 = 0;
+    for c in hex.chars() {
+        let nibble = c.to_digit(16).ok_or_else(|| format!("invalid hex digit '{}'", c))?;
+        low_bits_value = (low_bits_value << NIBBLE_BITS) | nibble as u128;
+    }
+
+    let value = if bits == 0 { 0 } else { low_bits_value << (128 - bits) };
+    Ok((bits, value))
+}
+
+/// Serves `GET /merkle-root` and `GET /merkle-children?prefix=...` so a
+/// peer can walk this node's tree top-down: fetch the root, and for every
+/// prefix whose hash disagrees with its own, fetch that prefix's children
+/// and recurse only into the ones that still disagree. What's left once
+/// the descent bottoms out is the list of keys to re-fetch through the
+/// existing snapshot/append path. Mirrors the `warp`-based route setup in
+/// `metrics::start_metrics_api`.
+pub async fn start_merkle_api(db: Arc<ChronosDb>, port: u16) {
+    let root_db = db.clone();
+    let root = warp::path("merkle-root")
+    .and(warp::get())
+    .map(move || warp::reply::json(&hex::encode(root_db.merkle_root())));
+
+    let children_db = db.clone();
+    let children = warp::path("merkle-children")
+    .and(warp::get())
+    .and(warp::query::<ChildrenQuery>())
+    .map(move |q: ChildrenQuery| {
+        match parse_prefix(&q.prefix) {
+            Ok((bits, value)) => {
+                let children = children_db.merkle_children(bits, value);
+                let encoded: Vec<(u8, String)> = children.into_iter()
+                    .map(|c| (c.nibble, hex::encode(c.hash)))
+                    .collect();
+                warp::reply::json(&encoded)
+            }
+            Err(e) => warp::reply::json(&serde_json::json!({ "error": e })),
+        }
+    });
+
+    let routes = root.or(children);
+    warp::serve(routes).run(([0, 0, 0, 0], port)).await;
+}
+
+/// Fetches the children of `prefix` in `peer_addr`'s tree over its
+/// `merkle-children` route. A thin wrapper so `diverged_keys_against` reads
+/// as a plain recursive walk instead of juggling `reqwest` calls inline.
+async fn fetch_peer_children(client: &reqwest::Client, peer_addr: &str, prefix: &str) -> Result<Vec<MerkleChild>, String> {
+    let url = format!("http://{}/merkle-children?prefix={}", peer_addr, prefix);
+    let encoded: Vec<(u8, String)> = client.get(&url).send().await
+        .map_err(|e| e.to_string())?
+        .json().await
+        .map_err(|e| e.to_string())?;
+
+    encoded.into_iter()
+        .map(|(nibble, hex_hash)| {
+            let bytes = hex::decode(&hex_hash).map_err(|e| e.to_string())?;
+            let hash: [u8; 32] = bytes.try_into().map_err(|_| "peer returned a malformed hash".to_string())?;
+            Ok(MerkleChild { nibble, hash })
+        })
+        .collect()
+}
+
+/// Walks this node's tree against `peer_addr`'s over HTTP, descending only
+/// into prefixes whose hash disagrees, and returns the keys behind every
+/// leaf-level disagreement still outstanding once the descent bottoms out -
+/// the input for re-fetching those records
+/// through the existing snapshot/append path.
+pub async fn diverged_keys_against(db: &ChronosDb, peer_addr: &str) -> Result<Vec<u128>, String> {
+    let client = reqwest::Client::new();
+
+    let local_root = db.merkle_root();
+    let peer_root_hex: String = client.get(format!("http://{}/merkle-root", peer_addr))
+        .send().await.map_err(|e| e.to_string())?
+        .json().await.map_err(|e| e.to_string())?;
+    let peer_root_bytes = hex::decode(&peer_root_hex).map_err(|e| e.to_string())?;
+    if peer_root_bytes == local_root {
+        return Ok(vec![]);
+    }
+
+    let leaves = db.merkle_leaves();
+    let mut divergent_keys = Vec::new();
+    let mut frontier = vec![(0u32, 0u128, String::new())];
+
+    while let Some((bits, prefix, prefix_hex)) = frontier.pop() {
+        let local_children: Vec<MerkleChild> = (0..FANOUT)
+            .map(|nibble| {
+                let child_bits = bits + NIBBLE_BITS;
+                let child_prefix = prefix | ((nibble as u128) << (128 - child_bits));
+                MerkleChild { nibble: nibble as u8, hash: hash_range(&leaves, child_bits, child_prefix) }
+            })
+            .collect();
+        let peer_children = fetch_peer_children(&client, peer_addr, &prefix_hex).await?;
+
+        for (local, peer) in local_children.iter().zip(peer_children.iter()) {
+            if local.hash == peer.hash {
+                continue;
+            }
+
+            let child_bits = bits + NIBBLE_BITS;
+            let child_prefix = prefix | ((local.nibble as u128) << (128 - child_bits));
+            let child_hex = format!("{}{:x}", prefix_hex, local.nibble);
+
+            if child_bits >= MAX_PREFIX_BITS {
+                // Bottomed out: this single-key leaf disagrees.
+                divergent_keys.push(child_prefix);
+            } else {
+                frontier.push((child_bits, child_prefix, child_hex));
+            }
+        }
+    }
+
+    Ok(divergent_keys)
+}
@@ -0,0 +1,121 @@
+// src/metrics.rs
+//
+// Hand-rolled Prometheus text-exposition metrics, in keeping with this
+// crate's preference for small inline implementations over another
+// dependency (see `filter.rs`'s Bloom filter, `storage/chunking.rs`'s CDC).
+// Counters live on `ChronosDb` and are updated inline by `server.rs` and
+// `ChronosDb::compact`; `render` assembles them into scrape text on demand.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::sync::Arc;
+use warp::Filter;
+use crate::ChronosDb;
+
+/// Per-opcode request counts and cumulative latency, Raft write outcomes,
+/// and the most recent compaction's moved/dropped/reclaimed counts.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    op_counts: Mutex<HashMap<&'static str, u64>>,
+    op_latency_micros: Mutex<HashMap<&'static str, u64>>,
+    raft_writes_ok: AtomicU64,
+    raft_writes_err: AtomicU64,
+    last_compaction_moved: AtomicU64,
+    last_compaction_dropped: AtomicU64,
+    last_compaction_reclaimed_bytes: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tallies one request of `op`, adding `elapsed` to its cumulative
+    /// latency so the scrape endpoint can expose
+    /// `chronos_request_latency_micros_sum` next to `chronos_requests_total`.
+    pub fn record_op(&self, op: &'static str, elapsed: Duration) {
+        *self.op_counts.lock().unwrap().entry(op).or_insert(0) += 1;
+        *self.op_latency_micros.lock().unwrap().entry(op).or_insert(0) += elapsed.as_micros() as u64;
+    }
+
+    /// Records whether a `raft.client_write` call succeeded.
+    pub fn record_raft_write(&self, ok: bool) {
+        if ok {
+            self.raft_writes_ok.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.raft_writes_err.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Replaces the last-compaction gauges with the outcome of the most
+    /// recent `ChronosDb::compact` run.
+    pub fn record_compaction(&self, moved: u64, dropped: u64, reclaimed_bytes: u64) {
+        self.last_compaction_moved.store(moved, Ordering::Relaxed);
+        self.last_compaction_dropped.store(dropped, Ordering::Relaxed);
+        self.last_compaction_reclaimed_bytes.store(reclaimed_bytes, Ordering::Relaxed);
+    }
+
+    /// Renders every counter as Prometheus text exposition format. A few
+    /// gauges (vector index size, Bloom filter fill, segment bytes) are
+    /// read straight from `db` at scrape time instead of being cached here,
+    /// so they're never stale between scrapes.
+    pub fn render(&self, db: &ChronosDb) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP chronos_requests_total Total requests handled, by opcode.\n");
+        out.push_str("# TYPE chronos_requests_total counter\n");
+        for (op, count) in self.op_counts.lock().unwrap().iter() {
+            out.push_str(&format!("chronos_requests_total{{op=\"{}\"}} {}\n", op, count));
+        }
+
+        out.push_str("# HELP chronos_request_latency_micros_sum Cumulative request latency in microseconds, by opcode.\n");
+        out.push_str("# TYPE chronos_request_latency_micros_sum counter\n");
+        for (op, micros) in self.op_latency_micros.lock().unwrap().iter() {
+            out.push_str(&format!("chronos_request_latency_micros_sum{{op=\"{}\"}} {}\n", op, micros));
+        }
+
+        out.push_str("# HELP chronos_raft_writes_total Raft client_write outcomes, by result.\n");
+        out.push_str("# TYPE chronos_raft_writes_total counter\n");
+        out.push_str(&format!("chronos_raft_writes_total{{result=\"ok\"}} {}\n", self.raft_writes_ok.load(Ordering::Relaxed)));
+        out.push_str(&format!("chronos_raft_writes_total{{result=\"error\"}} {}\n", self.raft_writes_err.load(Ordering::Relaxed)));
+
+        out.push_str("# HELP chronos_vector_index_nodes Live nodes in the HNSW graph.\n");
+        out.push_str("# TYPE chronos_vector_index_nodes gauge\n");
+        out.push_str(&format!("chronos_vector_index_nodes {}\n", db.vector_index.len()));
+
+        out.push_str("# HELP chronos_bloom_filter_fill_ratio Average fill ratio of each shard's active Bloom filter stage.\n");
+        out.push_str("# TYPE chronos_bloom_filter_fill_ratio gauge\n");
+        out.push_str(&format!("chronos_bloom_filter_fill_ratio {}\n", db.bloom_fill_ratio()));
+
+        out.push_str("# HELP chronos_segment_bytes Bytes stored across all shards' active segments.\n");
+        out.push_str("# TYPE chronos_segment_bytes gauge\n");
+        out.push_str(&format!("chronos_segment_bytes {}\n", db.storage_bytes()));
+
+        out.push_str("# HELP chronos_last_compaction_moved Live records carried forward by the most recent compaction.\n");
+        out.push_str("# TYPE chronos_last_compaction_moved gauge\n");
+        out.push_str(&format!("chronos_last_compaction_moved {}\n", self.last_compaction_moved.load(Ordering::Relaxed)));
+
+        out.push_str("# HELP chronos_last_compaction_dropped Stale versions pruned by the most recent compaction.\n");
+        out.push_str("# TYPE chronos_last_compaction_dropped gauge\n");
+        out.push_str(&format!("chronos_last_compaction_dropped {}\n", self.last_compaction_dropped.load(Ordering::Relaxed)));
+
+        out.push_str("# HELP chronos_last_compaction_reclaimed_bytes Disk bytes reclaimed by the most recent compaction.\n");
+        out.push_str("# TYPE chronos_last_compaction_reclaimed_bytes gauge\n");
+        out.push_str(&format!("chronos_last_compaction_reclaimed_bytes {}\n", self.last_compaction_reclaimed_bytes.load(Ordering::Relaxed)));
+
+        out
+    }
+}
+
+/// Serves `GET /metrics` in Prometheus text exposition format, so the
+/// cluster can be scraped without a separate agent. Mirrors the
+/// `warp`-based route setup in `cluster::api::start_raft_api`.
+pub async fn start_metrics_api(db: Arc<ChronosDb>, port: u16) {
+    let metrics_route = warp::path("metrics")
+    .and(warp::get())
+    .map(move || db.metrics.render(&db));
+
+    warp::serve(metrics_route).run(([0, 0, 0, 0], port)).await;
+}
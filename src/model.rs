@@ -26,6 +26,12 @@ pub struct Record {
     /// Raw binary payload
     pub payload: Vec<u8>,
 
+    /// Opaque key this record is ordered by in `ChronosDb`'s secondary
+    /// range index (see `ChronosDb::range_scan`). Empty when the caller
+    /// didn't attach one, in which case the record is simply absent from
+    /// that index.
+    pub sort_key: Vec<u8>,
+
     /// When this fact was true in the real world
     pub valid_time: TimeStamp,
 
@@ -34,11 +40,12 @@ pub struct Record {
 }
 
 impl Record {
-    pub fn new(key: Uuid, vector: Vec<f32>, payload: Vec<u8>, ts: u64) -> Self {
+    pub fn new(key: Uuid, vector: Vec<f32>, payload: Vec<u8>, sort_key: Vec<u8>, ts: u64) -> Self {
         Self {
             key: key.as_u128(),
             vector,
             payload,
+            sort_key,
             // Start time is explicit (provided by Raft log) ensuring deterministic history
             valid_time: TimeStamp { start: ts, end: u64::MAX },
             tx_time: ts,
@@ -1,26 +1,232 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag_no_case, take_until, take_while},
+    bytes::complete::{tag, tag_no_case, take_until, take_while, take_while1},
     character::complete::{char, digit1, multispace0, multispace1},
-    combinator::{map_res, opt, recognize},
-    multi::separated_list1,
+    combinator::{map, map_res, opt, recognize},
+    multi::{many0, separated_list1},
     sequence::{delimited, preceded, tuple, terminated},
     IResult,
 };
+use std::io::{self, Cursor, Read};
 use uuid::Uuid;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Command {
     Insert { vector: Vec<f32>, payload: String, id: Option<Uuid> },
-    Select { vector: Option<Vec<f32>>, filter_id: Option<Uuid>, as_of: Option<u64>, limit: usize },
+    Select { vector: Option<Vec<f32>>, filter_id: Option<Uuid>, filter: Option<Filter>, as_of: Option<u64>, limit: usize },
     Update { id: Uuid, vector: Option<Vec<f32>>, payload: Option<String> },
     Delete { id: Uuid },
     Get { id: Uuid },
     History { id: Uuid },
+    /// Bulk-load every `INSERT` statement found in `path`, one per line, as
+    /// a single `OP_BATCH` request instead of one connection per record.
+    Load { path: String },
     Help,
     Exit,
 }
 
+// --- METADATA FILTER AST ---
+//
+// Predicates over a record's `payload`, treated as optionally-JSON: a key
+// like `payload.category` looks up that top-level field. Records whose
+// payload isn't JSON, or is missing the key, simply never match.
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FilterValue {
+    Str(String),
+    Num(f64),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Filter {
+    Cmp { key: String, op: FilterOp, value: FilterValue },
+    In { key: String, values: Vec<FilterValue> },
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    /// Evaluate this filter against a record's raw payload bytes, parsed as
+    /// JSON on the fly. A non-JSON or non-object payload never matches.
+    pub fn matches(&self, payload: &[u8]) -> bool {
+        match serde_json::from_slice::<serde_json::Value>(payload) {
+            Ok(value) => self.matches_value(&value),
+            Err(_) => false,
+        }
+    }
+
+    fn matches_value(&self, value: &serde_json::Value) -> bool {
+        match self {
+            Filter::Cmp { key, op, value: target } => {
+                value.get(key).map_or(false, |field| compare(field, *op, target))
+            }
+            Filter::In { key, values } => {
+                value.get(key).map_or(false, |field| {
+                    values.iter().any(|v| compare(field, FilterOp::Eq, v))
+                })
+            }
+            Filter::And(a, b) => a.matches_value(value) && b.matches_value(value),
+            Filter::Or(a, b) => a.matches_value(value) || b.matches_value(value),
+        }
+    }
+
+    // --- Manual binary framing, for shipping a Filter over the wire
+    // protocol (mirrors the hand-rolled framing used elsewhere in this
+    // crate, e.g. `HnswIndex::save`/`load`). ---
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Filter::Cmp { key, op, value } => {
+                out.push(0);
+                encode_str(out, key);
+                out.push(*op as u8);
+                encode_value(out, value);
+            }
+            Filter::In { key, values } => {
+                out.push(1);
+                encode_str(out, key);
+                out.extend_from_slice(&(values.len() as u16).to_le_bytes());
+                for v in values {
+                    encode_value(out, v);
+                }
+            }
+            Filter::And(a, b) => {
+                out.push(2);
+                a.encode(out);
+                b.encode(out);
+            }
+            Filter::Or(a, b) => {
+                out.push(3);
+                a.encode(out);
+                b.encode(out);
+            }
+        }
+    }
+
+    pub fn decode(cursor: &mut Cursor<&[u8]>) -> io::Result<Filter> {
+        let tag = read_u8(cursor)?;
+        match tag {
+            0 => {
+                let key = decode_str(cursor)?;
+                let op = decode_op(read_u8(cursor)?)?;
+                let value = decode_value(cursor)?;
+                Ok(Filter::Cmp { key, op, value })
+            }
+            1 => {
+                let key = decode_str(cursor)?;
+                let count = read_u16(cursor)?;
+                let mut values = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    values.push(decode_value(cursor)?);
+                }
+                Ok(Filter::In { key, values })
+            }
+            2 => Ok(Filter::And(Box::new(Filter::decode(cursor)?), Box::new(Filter::decode(cursor)?))),
+            3 => Ok(Filter::Or(Box::new(Filter::decode(cursor)?), Box::new(Filter::decode(cursor)?))),
+            other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown filter tag: {}", other))),
+        }
+    }
+}
+
+fn compare(field: &serde_json::Value, op: FilterOp, target: &FilterValue) -> bool {
+    match (field, target) {
+        (serde_json::Value::String(s), FilterValue::Str(t)) => match op {
+            FilterOp::Eq => s == t,
+            FilterOp::Ne => s != t,
+            FilterOp::Lt => s < t,
+            FilterOp::Gt => s > t,
+            FilterOp::Le => s <= t,
+            FilterOp::Ge => s >= t,
+        },
+        (serde_json::Value::Number(n), FilterValue::Num(t)) => {
+            let n = match n.as_f64() {
+                Some(n) => n,
+                None => return false,
+            };
+            match op {
+                FilterOp::Eq => n == *t,
+                FilterOp::Ne => n != *t,
+                FilterOp::Lt => n < *t,
+                FilterOp::Gt => n > *t,
+                FilterOp::Le => n <= *t,
+                FilterOp::Ge => n >= *t,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn encode_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode_value(out: &mut Vec<u8>, value: &FilterValue) {
+    match value {
+        FilterValue::Str(s) => {
+            out.push(0);
+            encode_str(out, s);
+        }
+        FilterValue::Num(n) => {
+            out.push(1);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+fn read_u8(cursor: &mut Cursor<&[u8]>) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    cursor.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16(cursor: &mut Cursor<&[u8]>) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    cursor.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn decode_str(cursor: &mut Cursor<&[u8]>) -> io::Result<String> {
+    let len = read_u16(cursor)? as usize;
+    let mut bytes = vec![0u8; len];
+    cursor.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+fn decode_value(cursor: &mut Cursor<&[u8]>) -> io::Result<FilterValue> {
+    match read_u8(cursor)? {
+        0 => Ok(FilterValue::Str(decode_str(cursor)?)),
+        1 => {
+            let mut buf = [0u8; 8];
+            cursor.read_exact(&mut buf)?;
+            Ok(FilterValue::Num(f64::from_le_bytes(buf)))
+        }
+        other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown filter value tag: {}", other))),
+    }
+}
+
+fn decode_op(byte: u8) -> io::Result<FilterOp> {
+    match byte {
+        0 => Ok(FilterOp::Eq),
+        1 => Ok(FilterOp::Ne),
+        2 => Ok(FilterOp::Lt),
+        3 => Ok(FilterOp::Gt),
+        4 => Ok(FilterOp::Le),
+        5 => Ok(FilterOp::Ge),
+        other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown filter op: {}", other))),
+    }
+}
+
 // --- BASIC PARSERS ---
 
 fn parse_float(input: &str) -> IResult<&str, f32> {
@@ -62,6 +268,84 @@ fn parse_uuid(input: &str) -> IResult<&str, Uuid> {
     }
 }
 
+fn parse_filter_num(input: &str) -> IResult<&str, f64> {
+    let (input, num_str) = recognize(tuple((
+        opt(char('-')),
+                                            digit1,
+                                            opt(tuple((char('.'), digit1))),
+    )))(input)?;
+    match num_str.parse::<f64>() {
+        Ok(n) => Ok((input, n)),
+        Err(_) => Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Float))),
+    }
+}
+
+fn parse_filter_value(input: &str) -> IResult<&str, FilterValue> {
+    alt((
+        map(parse_quoted_string, FilterValue::Str),
+        map(parse_filter_num, FilterValue::Num),
+    ))(input)
+}
+
+fn parse_filter_op(input: &str) -> IResult<&str, FilterOp> {
+    alt((
+        map(tag("!="), |_| FilterOp::Ne),
+        map(tag("<="), |_| FilterOp::Le),
+        map(tag(">="), |_| FilterOp::Ge),
+        map(tag("="), |_| FilterOp::Eq),
+        map(tag("<"), |_| FilterOp::Lt),
+        map(tag(">"), |_| FilterOp::Gt),
+    ))(input)
+}
+
+fn parse_payload_key(input: &str) -> IResult<&str, String> {
+    let (input, _) = tag_ci("payload")(input)?;
+    let (input, _) = char('.')(input)?;
+    let (input, key) = take_while1(|c: char| c.is_alphanumeric() || c == '_')(input)?;
+    Ok((input, key.to_string()))
+}
+
+fn parse_filter_predicate(input: &str) -> IResult<&str, Filter> {
+    let (input, key) = parse_payload_key(input)?;
+    let (input, _) = multispace1(input)?;
+
+    alt((
+        move |i| {
+            let (i, _) = ws(tag_ci("IN"))(i)?;
+            let (i, _) = ws(char('('))(i)?;
+            let (i, values) = separated_list1(ws(char(',')), parse_filter_value)(i)?;
+            let (i, _) = ws(char(')'))(i)?;
+            Ok((i, Filter::In { key: key.clone(), values }))
+        },
+        move |i| {
+            let (i, op) = ws(parse_filter_op)(i)?;
+            let (i, value) = ws(parse_filter_value)(i)?;
+            Ok((i, Filter::Cmp { key: key.clone(), op, value }))
+        },
+    ))(input)
+}
+
+/// A chain of `payload.<key> <op> <value>` predicates joined by `AND`/`OR`,
+/// left-associative (no precedence between the two, matching the rest of
+/// this grammar's simplicity).
+fn parse_filter(input: &str) -> IResult<&str, Filter> {
+    let (input, first) = parse_filter_predicate(input)?;
+    let (input, rest) = many0(tuple((
+        ws(alt((tag_ci("AND"), tag_ci("OR")))),
+        parse_filter_predicate,
+    )))(input)?;
+
+    let filter = rest.into_iter().fold(first, |acc, (conj, next)| {
+        if conj.eq_ignore_ascii_case("AND") {
+            Filter::And(Box::new(acc), Box::new(next))
+        } else {
+            Filter::Or(Box::new(acc), Box::new(next))
+        }
+    });
+
+    Ok((input, filter))
+}
+
 // --- HELPERS ---
 fn ws<'a, F, O, E: nom::error::ParseError<&'a str>>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
 where F: FnMut(&'a str) -> IResult<&'a str, O, E> {
@@ -104,13 +388,19 @@ fn parse_select(input: &str) -> IResult<&str, Command> {
                                        parse_vector
     ))(input)?;
 
-    let (input, _) = opt(ws(tag_ci("AND")))(input)?;
+    let (input, _) = opt(ws(alt((tag_ci("AND"), tag_ci("WHERE")))))(input)?;
 
     let (input, filter_id) = opt(preceded(
         tuple((ws(tag_ci("ID")), ws(char('=')), opt(char('\'')))),
                                           terminated(parse_uuid, opt(char('\'')))
     ))(input)?;
 
+    let (input, _) = opt(ws(alt((tag_ci("AND"), tag_ci("WHERE")))))(input)?;
+
+    let (input, filter) = opt(parse_filter)(input)?;
+
+    let (input, _) = opt(ws(tag_ci("AND")))(input)?;
+
     let (input, as_of) = opt(preceded(
         tuple((ws(tag_ci("AS")), ws(tag_ci("OF")))),
                                       parse_u64
@@ -121,7 +411,7 @@ fn parse_select(input: &str) -> IResult<&str, Command> {
                                       map_res(digit1, |s: &str| s.parse::<usize>())
     ))(input)?;
 
-    Ok((input, Command::Select { vector, filter_id, as_of, limit: limit.unwrap_or(5) }))
+    Ok((input, Command::Select { vector, filter_id, filter, as_of, limit: limit.unwrap_or(5) }))
 }
 
 fn parse_update(input: &str) -> IResult<&str, Command> {
@@ -170,6 +460,14 @@ fn parse_history(input: &str) -> IResult<&str, Command> {
     Ok((input, Command::History { id }))
 }
 
+fn parse_load(input: &str) -> IResult<&str, Command> {
+    let (input, _) = tag_ci("LOAD")(input)?;
+    let (input, _) = opt(ws(tag_ci("FILE")))(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, path) = parse_quoted_string(input)?;
+    Ok((input, Command::Load { path }))
+}
+
 fn parse_help(input: &str) -> IResult<&str, Command> {
     let (input, _) = tag_ci("HELP")(input)?;
     Ok((input, Command::Help))
@@ -189,6 +487,7 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
         parse_delete,
         parse_get,
         parse_history,
+        parse_load,
         parse_help,
         parse_exit,
     ))(input);
@@ -0,0 +1,246 @@
+use std::io::{self, Read, Write};
+use rand::seq::SliceRandom;
+use crate::vector::Metric;
+
+/// Whether an `HnswIndex` stores full-precision vectors ("Exact") or
+/// `ProductQuantizer`-encoded centroid ids ("Pq"). A collection picks one at
+/// construction time; switching later means rebuilding the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexMode {
+    Exact,
+    Pq,
+}
+
+/// Centroids per subspace. Fixed at 256 so a centroid id fits in one byte,
+/// which is the whole point of PQ: M bytes per vector instead of 4*D.
+const CENTROIDS_PER_SUBSPACE: usize = 256;
+const TRAIN_ITERATIONS: usize = 15;
+
+/// Splits a D-dim vector into `m` contiguous subvectors and replaces each
+/// with the id of its nearest of 256 centroids (trained via k-means on a
+/// sample set), so a stored vector shrinks from `4*D` bytes to `m` bytes.
+/// Query-time distance is then an asymmetric distance computation: a
+/// precomputed `m * 256` lookup table is built once per query, and scoring a
+/// candidate is `m` table lookups instead of `D` float ops.
+#[derive(Debug, Clone)]
+pub struct ProductQuantizer {
+    m: usize,
+    sub_dim: usize,
+    // `m` codebooks, each `CENTROIDS_PER_SUBSPACE * sub_dim` floats flattened.
+    codebooks: Vec<Vec<f32>>,
+}
+
+impl ProductQuantizer {
+    /// Train a quantizer on a sample of full-precision vectors. `m` must
+    /// evenly divide the vectors' dimensionality.
+    pub fn train(samples: &[Vec<f32>], m: usize) -> Self {
+        assert!(!samples.is_empty(), "cannot train a quantizer with no sample vectors");
+        let dim = samples[0].len();
+        assert_eq!(dim % m, 0, "subspace count must evenly divide vector dimensionality");
+        let sub_dim = dim / m;
+
+        let mut rng = rand::thread_rng();
+        let mut codebooks = Vec::with_capacity(m);
+
+        for j in 0..m {
+            let subvectors: Vec<&[f32]> = samples.iter()
+            .map(|v| &v[j * sub_dim..(j + 1) * sub_dim])
+            .collect();
+
+            codebooks.push(Self::train_subspace(&subvectors, sub_dim, &mut rng));
+        }
+
+        Self { m, sub_dim, codebooks }
+    }
+
+    fn train_subspace(subvectors: &[&[f32]], sub_dim: usize, rng: &mut impl rand::Rng) -> Vec<f32> {
+        // Seed centroids from the sample set itself (with replacement if the
+        // sample is smaller than the target centroid count).
+        let mut centroids: Vec<f32> = Vec::with_capacity(CENTROIDS_PER_SUBSPACE * sub_dim);
+        for _ in 0..CENTROIDS_PER_SUBSPACE {
+            let pick = subvectors.choose(rng).expect("sample set must be non-empty");
+            centroids.extend_from_slice(pick);
+        }
+
+        let mut assignment = vec![0usize; subvectors.len()];
+
+        for _ in 0..TRAIN_ITERATIONS {
+            // Assign each subvector to its nearest centroid.
+            for (i, sv) in subvectors.iter().enumerate() {
+                let mut best = 0usize;
+                let mut best_dist = f32::MAX;
+                for c in 0..CENTROIDS_PER_SUBSPACE {
+                    let centroid = &centroids[c * sub_dim..(c + 1) * sub_dim];
+                    let d = squared_euclidean(sv, centroid);
+                    if d < best_dist {
+                        best_dist = d;
+                        best = c;
+                    }
+                }
+                assignment[i] = best;
+            }
+
+            // Recompute each centroid as the mean of its assigned subvectors.
+            let mut sums = vec![0f32; CENTROIDS_PER_SUBSPACE * sub_dim];
+            let mut counts = vec![0u32; CENTROIDS_PER_SUBSPACE];
+            for (sv, &c) in subvectors.iter().zip(assignment.iter()) {
+                counts[c] += 1;
+                let base = c * sub_dim;
+                for d in 0..sub_dim {
+                    sums[base + d] += sv[d];
+                }
+            }
+
+            for c in 0..CENTROIDS_PER_SUBSPACE {
+                if counts[c] == 0 {
+                    continue; // keep the previous centroid; nothing assigned this round
+                }
+                let base = c * sub_dim;
+                for d in 0..sub_dim {
+                    centroids[base + d] = sums[base + d] / counts[c] as f32;
+                }
+            }
+        }
+
+        centroids
+    }
+
+    /// Number of subvectors (and therefore bytes) per encoded vector.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Replace each subvector with its nearest centroid's id.
+    pub fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        let mut codes = Vec::with_capacity(self.m);
+        for j in 0..self.m {
+            let sub = &vector[j * self.sub_dim..(j + 1) * self.sub_dim];
+            let codebook = &self.codebooks[j];
+
+            let mut best = 0u8;
+            let mut best_dist = f32::MAX;
+            for c in 0..CENTROIDS_PER_SUBSPACE {
+                let centroid = &codebook[c * self.sub_dim..(c + 1) * self.sub_dim];
+                let d = squared_euclidean(sub, centroid);
+                if d < best_dist {
+                    best_dist = d;
+                    best = c as u8;
+                }
+            }
+            codes.push(best);
+        }
+        codes
+    }
+
+    /// Reconstruct an approximate vector by concatenating the codes' centroids.
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.m * self.sub_dim);
+        for (j, &code) in codes.iter().enumerate() {
+            let codebook = &self.codebooks[j];
+            let base = code as usize * self.sub_dim;
+            out.extend_from_slice(&codebook[base..base + self.sub_dim]);
+        }
+        out
+    }
+
+    /// Precompute the `m * 256` table of distances between `query`'s
+    /// subvectors and every centroid, under `metric`'s convention (lower is
+    /// closer). Reused across every candidate scored against this query.
+    pub fn build_table(&self, query: &[f32], metric: Metric) -> Vec<f32> {
+        let mut table = vec![0f32; self.m * CENTROIDS_PER_SUBSPACE];
+        for j in 0..self.m {
+            let sub = &query[j * self.sub_dim..(j + 1) * self.sub_dim];
+            let codebook = &self.codebooks[j];
+            for c in 0..CENTROIDS_PER_SUBSPACE {
+                let centroid = &codebook[c * self.sub_dim..(c + 1) * self.sub_dim];
+                table[j * CENTROIDS_PER_SUBSPACE + c] = metric.distance(sub, centroid);
+            }
+        }
+        table
+    }
+
+    /// Asymmetric distance computation: sum the `m` table entries a code
+    /// sequence points to. No float arithmetic on the hot path - just byte
+    /// indexed table adds.
+    pub fn asymmetric_distance(&self, table: &[f32], codes: &[u8]) -> f32 {
+        codes.iter().enumerate()
+        .map(|(j, &c)| table[j * CENTROIDS_PER_SUBSPACE + c as usize])
+        .sum()
+    }
+
+    /// Persist the codebooks so they don't need retraining after a restart.
+    /// Written as part of `HnswIndex::save`'s file, mirroring its manual
+    /// little-endian framing.
+    pub fn save<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.m as u32).to_le_bytes())?;
+        writer.write_all(&(self.sub_dim as u32).to_le_bytes())?;
+        for codebook in &self.codebooks {
+            for val in codebook {
+                writer.write_all(&val.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut u32_buf = [0u8; 4];
+
+        reader.read_exact(&mut u32_buf)?;
+        let m = u32::from_le_bytes(u32_buf) as usize;
+
+        reader.read_exact(&mut u32_buf)?;
+        let sub_dim = u32::from_le_bytes(u32_buf) as usize;
+
+        let mut codebooks = Vec::with_capacity(m);
+        let mut f32_buf = [0u8; 4];
+        for _ in 0..m {
+            let mut codebook = Vec::with_capacity(CENTROIDS_PER_SUBSPACE * sub_dim);
+            for _ in 0..(CENTROIDS_PER_SUBSPACE * sub_dim) {
+                reader.read_exact(&mut f32_buf)?;
+                codebook.push(f32::from_le_bytes(f32_buf));
+            }
+            codebooks.push(codebook);
+        }
+
+        Ok(Self { m, sub_dim, codebooks })
+    }
+}
+
+fn squared_euclidean(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asymmetric_distance_matches_full_precision_distance_to_the_decoded_vector() {
+        // Two well-separated clusters in a 4-dim space split into 2
+        // subspaces, so training converges onto centroids that sit right on
+        // the samples themselves.
+        let samples = vec![
+            vec![0.0, 0.0, 0.0, 0.0],
+            vec![0.0, 0.0, 0.0, 0.0],
+            vec![10.0, 10.0, 10.0, 10.0],
+            vec![10.0, 10.0, 10.0, 10.0],
+        ];
+        let quantizer = ProductQuantizer::train(&samples, 2);
+
+        let query = vec![10.0, 10.0, 10.0, 10.0];
+        let codes = quantizer.encode(&query);
+        let table = quantizer.build_table(&query, Metric::Euclidean);
+
+        let adc_distance = quantizer.asymmetric_distance(&table, &codes);
+        let decoded = quantizer.decode(&codes);
+        let full_distance = Metric::Euclidean.distance(&query, &decoded);
+
+        // Euclidean (squared) distance decomposes additively across
+        // subspaces, so the table-lookup sum must equal scoring the fully
+        // decoded vector directly.
+        assert!((adc_distance - full_distance).abs() < 1e-4, "{} vs {}", adc_distance, full_distance);
+        // The query sits on a trained centroid, so the decoded vector should
+        // reconstruct it exactly.
+        assert!(full_distance < 1e-6);
+    }
+}
@@ -1,13 +1,16 @@
 use std::io::Cursor;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
 use tokio::net::{TcpListener, TcpStream};
 use uuid::Uuid;
 
-use crate::ChronosDb;
+use openraft::error::{ClientWriteError, RaftError};
+
+use crate::{ChangeEvent, ChronosDb};
 use crate::model::VECTOR_DIM;
-use crate::cluster::types::{ChronosRaft, ChronosRequest};
+use crate::parser::Filter;
+use crate::cluster::types::{ChronosRaft, ChronosRequest, NodeId};
 
 // --- OpCodes ---
 const OP_INSERT: u8     = 0x01;
@@ -18,6 +21,10 @@ const OP_DELETE: u8     = 0x05;
 const OP_UPDATE: u8     = 0x06;
 const OP_GET_AS_OF: u8  = 0x07;
 const OP_COMPACT: u8    = 0x08;
+const OP_SEARCH_FILTERED: u8 = 0x09;
+const OP_BATCH: u8      = 0x0A;
+const OP_WATCH: u8      = 0x0B;
+const OP_RANGE: u8      = 0x0C;
 
 pub struct ChronosServer {
     db: Arc<ChronosDb>,
@@ -82,99 +89,253 @@ async fn handle_client(mut stream: TcpStream, db: Arc<ChronosDb>, raft: ChronosR
 
         let mut writer = BufWriter::new(&mut stream);
 
-        // 4. Process Command
-        match op_code {
+        // 4. Process Command, timing every dispatch for the metrics endpoint.
+        let start = Instant::now();
+        let op_name = match op_code {
             // Write Operations (Forward to Raft)
-            OP_INSERT => handle_insert(&mut writer, payload, &raft).await?,
-            OP_DELETE => handle_delete(&mut writer, payload, &raft).await?,
-            OP_UPDATE => handle_update(&mut writer, payload, &raft).await?,
+            OP_INSERT => { handle_insert(&mut writer, payload, &raft, &db).await?; "insert" }
+            OP_DELETE => { handle_delete(&mut writer, payload, &raft, &db).await?; "delete" }
+            OP_UPDATE => { handle_update(&mut writer, payload, &raft, &db).await?; "update" }
+            OP_BATCH  => { handle_batch(&mut writer, payload, &raft, &db).await?; "batch" }
 
             // Read Operations (Local DB)
-            OP_GET        => handle_get(&mut writer, payload, &db).await?,
-            OP_SEARCH     => handle_search(&mut writer, payload, &db).await?,
-            OP_HISTORY    => handle_history(&mut writer, payload, &db).await?,
-            OP_GET_AS_OF  => handle_get_as_of(&mut writer, payload, &db).await?,
+            OP_GET        => { handle_get(&mut writer, payload, &db).await?; "get" }
+            OP_SEARCH     => { handle_search(&mut writer, payload, &db).await?; "search" }
+            OP_SEARCH_FILTERED => { handle_search_filtered(&mut writer, payload, &db).await?; "search_filtered" }
+            OP_HISTORY    => { handle_history(&mut writer, payload, &db).await?; "history" }
+            OP_GET_AS_OF  => { handle_get_as_of(&mut writer, payload, &db).await?; "get_as_of" }
+            OP_WATCH      => { handle_watch(&mut writer, payload, &db).await?; "watch" }
+            OP_RANGE      => { handle_range(&mut writer, payload, &db).await?; "range" }
 
             // Maintenance
-            OP_COMPACT    => handle_compact(&mut writer, payload, &db).await?,
+            OP_COMPACT    => { handle_compact(&mut writer, payload, &db).await?; "compact" }
 
             _ => {
                 eprintln!("Unknown OpCode: 0x{:02X}", op_code);
                 return Ok(());
             }
-        }
+        };
+        db.metrics.record_op(op_name, start.elapsed());
         writer.flush().await?;
     }
 }
 
 // --- WRITE HANDLERS (RAFT) ---
 
-async fn handle_insert<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft) -> std::io::Result<()> {
+// Protocol: [UUID (16b)] [vector: VECTOR_DIM * f32] [sort_key_len: u32][sort_key] [payload: remainder]
+fn decode_insert_body(data: &[u8]) -> Option<(Uuid, Vec<f32>, Vec<u8>, Vec<u8>)> {
     let vec_size = VECTOR_DIM * 4;
-    if data.len() < 16 + vec_size {
-        writer.write_all(b"ER").await?;
-        return Ok(());
+    if data.len() < 16 + vec_size + 4 {
+        return None;
     }
 
     let mut cursor = Cursor::new(data);
 
     let mut uuid_bytes = [0u8; 16];
-    std::io::Read::read_exact(&mut cursor, &mut uuid_bytes).unwrap();
+    std::io::Read::read_exact(&mut cursor, &mut uuid_bytes).ok()?;
     let id = Uuid::from_bytes(uuid_bytes);
 
     let mut vector = Vec::with_capacity(VECTOR_DIM);
     let mut f32_buf = [0u8; 4];
     for _ in 0..VECTOR_DIM {
-        std::io::Read::read_exact(&mut cursor, &mut f32_buf).unwrap();
+        std::io::Read::read_exact(&mut cursor, &mut f32_buf).ok()?;
         vector.push(f32::from_le_bytes(f32_buf));
     }
 
-    let payload_pos = cursor.position() as usize;
-    let payload = data[payload_pos..].to_vec();
+    let mut sort_key_len_buf = [0u8; 4];
+    std::io::Read::read_exact(&mut cursor, &mut sort_key_len_buf).ok()?;
+    let sort_key_len = u32::from_le_bytes(sort_key_len_buf) as usize;
 
-    // Capture deterministic timestamp for Raft State Machine
-    let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let sort_key_pos = cursor.position() as usize;
+    if sort_key_pos + sort_key_len > data.len() {
+        return None;
+    }
+    let sort_key = data[sort_key_pos..sort_key_pos + sort_key_len].to_vec();
+
+    let payload = data[sort_key_pos + sort_key_len..].to_vec();
+    Some((id, vector, sort_key, payload))
+}
 
-    let req = ChronosRequest::Insert { id, vector, payload, ts };
+// Protocol: [UUID (16b)] [payload: remainder]
+fn decode_update_body(data: &[u8]) -> Option<(Uuid, Vec<u8>)> {
+    if data.len() < 16 {
+        return None;
+    }
+    let id = Uuid::from_bytes(data[..16].try_into().ok()?);
+    let payload = data[16..].to_vec();
+    Some((id, payload))
+}
 
-    match raft.client_write(req).await {
-        Ok(_) => writer.write_all(b"OK").await?,
+// Protocol: [UUID (16b)]
+fn decode_delete_body(data: &[u8]) -> Option<Uuid> {
+    if data.len() != 16 {
+        return None;
+    }
+    Some(Uuid::from_bytes(data.try_into().ok()?))
+}
+
+/// If `err` is openraft's `ForwardToLeader` (we're not the leader but know
+/// who is), returns that leader's address so the caller can redirect.
+/// `None` either for an unrelated error or a `ForwardToLeader` with no
+/// known leader yet (mid-election).
+fn leader_addr(err: &RaftError<NodeId, ClientWriteError<NodeId, openraft::BasicNode>>) -> Option<String> {
+    match err {
+        RaftError::APIError(ClientWriteError::ForwardToLeader(fwd)) => {
+            fwd.leader_node.as_ref().map(|n| n.addr.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Replies `OK`, or on failure either `LR` (leader redirect) followed by a
+/// length-prefixed leader address - empty if no leader is known yet - or
+/// `ER` for anything else. Shared by the single-record write handlers so a
+/// CLI talking to a follower can hand the request to the right node instead
+/// of just failing.
+async fn write_write_result<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    result: Result<(), RaftError<NodeId, ClientWriteError<NodeId, openraft::BasicNode>>>,
+    db: &Arc<ChronosDb>,
+) -> std::io::Result<()> {
+    match result {
+        Ok(()) => {
+            db.metrics.record_raft_write(true);
+            writer.write_all(b"OK").await?;
+        }
         Err(e) => {
-            eprintln!("Raft Write Error: {:?}", e);
-            writer.write_all(b"ER").await?;
+            db.metrics.record_raft_write(false);
+            match leader_addr(&e) {
+                Some(addr) => {
+                    writer.write_all(b"LR").await?;
+                    writer.write_all(&(addr.len() as u32).to_le_bytes()).await?;
+                    writer.write_all(addr.as_bytes()).await?;
+                }
+                None => {
+                    eprintln!("Raft Write Error: {:?}", e);
+                    writer.write_all(b"ER").await?;
+                    writer.write_all(&0u32.to_le_bytes()).await?;
+                }
+            }
         }
     }
     Ok(())
 }
 
-async fn handle_update<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft) -> std::io::Result<()> {
-    if data.len() < 16 {
-        writer.write_all(b"ER").await?;
-        return Ok(());
-    }
+async fn handle_insert<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft, db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    let (id, vector, sort_key, payload) = match decode_insert_body(data) {
+        Some(parts) => parts,
+        None => {
+            writer.write_all(b"ER").await?;
+            writer.write_all(&0u32.to_le_bytes()).await?;
+            return Ok(());
+        }
+    };
 
-    let id = Uuid::from_bytes(data[..16].try_into().unwrap());
-    let payload = data[16..].to_vec();
+    // Capture deterministic timestamp for Raft State Machine
     let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
 
-    let req = ChronosRequest::Update { id, payload, ts };
+    let req = ChronosRequest::Insert { id, vector, payload, sort_key, ts };
+    let result = raft.client_write(req).await.map(|_| ());
+    write_write_result(writer, result, db).await
+}
 
-    match raft.client_write(req).await {
-        Ok(_) => writer.write_all(b"OK").await?,
-        Err(_) => writer.write_all(b"ER").await?,
-    }
-    Ok(())
+async fn handle_update<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft, db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    let (id, payload) = match decode_update_body(data) {
+        Some(parts) => parts,
+        None => {
+            writer.write_all(b"ER").await?;
+            writer.write_all(&0u32.to_le_bytes()).await?;
+            return Ok(());
+        }
+    };
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    let req = ChronosRequest::Update { id, payload, ts };
+    let result = raft.client_write(req).await.map(|_| ());
+    write_write_result(writer, result, db).await
 }
 
-async fn handle_delete<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft) -> std::io::Result<()> {
-    if data.len() != 16 { return Ok(()); }
-    let id = Uuid::from_bytes(data.try_into().unwrap());
+async fn handle_delete<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft, db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    let id = match decode_delete_body(data) {
+        Some(id) => id,
+        None => return Ok(()),
+    };
 
     let req = ChronosRequest::Delete { id };
+    let result = raft.client_write(req).await.map(|_| ());
+    write_write_result(writer, result, db).await
+}
+
+// Protocol: [count: u32] { [tag: u8][item_len: u32][item body, framed like the
+// matching OP_INSERT/OP_UPDATE/OP_DELETE payload] } * count
+//
+// Borrowed from Garage's K2V batch API: the whole group commits as a single
+// Raft entry (see `ChronosRequest::Batch`), so a bulk load pays for one
+// consensus round trip instead of one per record. Response is a
+// count-prefixed per-item OK/ER status vector; a malformed request frame,
+// or a Raft write that fails (e.g. this node isn't the leader), gets a
+// zero-length status vector back instead.
+async fn handle_batch<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], raft: &ChronosRaft, db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    let mut cursor = Cursor::new(data);
+
+    let mut count_buf = [0u8; 4];
+    if std::io::Read::read_exact(&mut cursor, &mut count_buf).is_err() {
+        writer.write_all(&0u32.to_le_bytes()).await?;
+        return Ok(());
+    }
+    let count = u32::from_le_bytes(count_buf) as usize;
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
 
-    match raft.client_write(req).await {
-        Ok(_) => writer.write_all(b"OK").await?,
-        Err(_) => writer.write_all(b"ER").await?,
+    let mut ops = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut tag_buf = [0u8; 1];
+        let mut len_buf = [0u8; 4];
+        let ok = std::io::Read::read_exact(&mut cursor, &mut tag_buf).is_ok()
+            && std::io::Read::read_exact(&mut cursor, &mut len_buf).is_ok();
+        if !ok {
+            writer.write_all(&0u32.to_le_bytes()).await?;
+            return Ok(());
+        }
+
+        let item_len = u32::from_le_bytes(len_buf) as usize;
+        let pos = cursor.position() as usize;
+        if pos + item_len > data.len() {
+            writer.write_all(&0u32.to_le_bytes()).await?;
+            return Ok(());
+        }
+        let item = &data[pos..pos + item_len];
+        cursor.set_position((pos + item_len) as u64);
+
+        let op = match tag_buf[0] {
+            OP_INSERT => decode_insert_body(item).map(|(id, vector, sort_key, payload)| ChronosRequest::Insert { id, vector, payload, sort_key, ts }),
+            OP_UPDATE => decode_update_body(item).map(|(id, payload)| ChronosRequest::Update { id, payload, ts }),
+            OP_DELETE => decode_delete_body(item).map(|id| ChronosRequest::Delete { id }),
+            _ => None,
+        };
+
+        match op {
+            Some(op) => ops.push(op),
+            None => {
+                writer.write_all(&0u32.to_le_bytes()).await?;
+                return Ok(());
+            }
+        }
+    }
+
+    match raft.client_write(ChronosRequest::Batch(ops)).await {
+        Ok(resp) => {
+            db.metrics.record_raft_write(true);
+            let item_results = resp.data.item_results;
+            writer.write_all(&(item_results.len() as u32).to_le_bytes()).await?;
+            for ok in item_results {
+                writer.write_all(if ok { b"OK" } else { b"ER" }).await?;
+            }
+        }
+        Err(e) => {
+            eprintln!("Raft Batch Write Error: {:?}", e);
+            db.metrics.record_raft_write(false);
+            writer.write_all(&0u32.to_le_bytes()).await?;
+        }
     }
     Ok(())
 }
@@ -223,6 +384,51 @@ async fn handle_get_as_of<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8],
     Ok(())
 }
 
+// Protocol: [UUID (16b)] [timeout_ms u64 (8b)]
+// Response:  0x00                              - timed out, nothing changed
+//            0x01 [len:u32][payload]            - Insert/Update landed
+//            0x02                                - Delete landed
+//
+// Follows Garage K2V's poll-item model: parks this connection's task on a
+// per-key broadcast channel (`ChronosDb::watch`) instead of the caller
+// busy-polling `OP_GET`, and returns as soon as one committed mutation to
+// `id` arrives or `timeout_ms` elapses.
+async fn handle_watch<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    if data.len() != 24 {
+        writer.write_all(&[0u8]).await?;
+        return Ok(());
+    }
+
+    let mut cursor = Cursor::new(data);
+    let mut uuid_bytes = [0u8; 16];
+    std::io::Read::read_exact(&mut cursor, &mut uuid_bytes).unwrap();
+    let id = Uuid::from_bytes(uuid_bytes);
+
+    let mut timeout_bytes = [0u8; 8];
+    std::io::Read::read_exact(&mut cursor, &mut timeout_bytes).unwrap();
+    let timeout_ms = u64::from_le_bytes(timeout_bytes);
+
+    let mut rx = db.watch(id);
+
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), rx.recv()).await {
+        Ok(Ok(ChangeEvent::Updated(payload))) => {
+            writer.write_all(&[1u8]).await?;
+            writer.write_all(&(payload.len() as u32).to_le_bytes()).await?;
+            writer.write_all(&payload).await?;
+        }
+        Ok(Ok(ChangeEvent::Deleted)) => {
+            writer.write_all(&[2u8]).await?;
+        }
+        // Lagged behind the channel's buffer, or every sender dropped -
+        // both are surfaced like a plain timeout so the client just
+        // re-issues the watch.
+        Ok(Err(_)) | Err(_) => {
+            writer.write_all(&[0u8]).await?;
+        }
+    }
+    Ok(())
+}
+
 async fn handle_search<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db: &Arc<ChronosDb>) -> std::io::Result<()> {
     let mut cursor = Cursor::new(data);
 
@@ -250,6 +456,39 @@ async fn handle_search<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db
     Ok(())
 }
 
+// Protocol: [k:u32][query vector: VECTOR_DIM * f32][filter, manually framed via Filter::encode]
+async fn handle_search_filtered<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    let mut cursor = Cursor::new(data);
+
+    let mut k_buf = [0u8; 4];
+    if std::io::Read::read_exact(&mut cursor, &mut k_buf).is_err() { return Ok(()); }
+    let k = u32::from_le_bytes(k_buf) as usize;
+
+    let mut query = Vec::with_capacity(VECTOR_DIM);
+    let mut f32_buf = [0u8; 4];
+    for _ in 0..VECTOR_DIM {
+        if std::io::Read::read_exact(&mut cursor, &mut f32_buf).is_err() { return Ok(()); }
+        query.push(f32::from_le_bytes(f32_buf));
+    }
+
+    let filter = match Filter::decode(&mut cursor) {
+        Ok(f) => f,
+        Err(_) => return Ok(()),
+    };
+
+    let results = db.filtered_vector_search(&query, Some(&filter), k);
+
+    let count = (results.len() as u32).to_le_bytes();
+    writer.write_all(&count).await?;
+
+    for (node_id, dist_sq) in results {
+        let uuid = Uuid::from_u128(node_id);
+        writer.write_all(uuid.as_bytes()).await?;
+        writer.write_all(&dist_sq.to_le_bytes()).await?;
+    }
+    Ok(())
+}
+
 async fn handle_history<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db: &Arc<ChronosDb>) -> std::io::Result<()> {
     if data.len() != 16 { return Ok(()); }
     let id = Uuid::from_bytes(data.try_into().unwrap());
@@ -270,6 +509,64 @@ async fn handle_history<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], d
     Ok(())
 }
 
+// Protocol: [start_key_len:u32][start_key][end_key_len:u32][end_key][limit:u32]
+// Response:  [count:u32] { [UUID (16b)][payload_len:u32][payload] } * count
+//            [continuation_len:u32][continuation_key]   - empty when the
+//            range was exhausted; otherwise resume by passing it back as
+//            `start_key` for the next call.
+async fn handle_range<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db: &Arc<ChronosDb>) -> std::io::Result<()> {
+    let mut cursor = Cursor::new(data);
+
+    let mut start_len_buf = [0u8; 4];
+    if std::io::Read::read_exact(&mut cursor, &mut start_len_buf).is_err() {
+        writer.write_all(&0u32.to_le_bytes()).await?;
+        return Ok(());
+    }
+    let start_len = u32::from_le_bytes(start_len_buf) as usize;
+    let mut start_key = vec![0u8; start_len];
+    if std::io::Read::read_exact(&mut cursor, &mut start_key).is_err() {
+        writer.write_all(&0u32.to_le_bytes()).await?;
+        return Ok(());
+    }
+
+    let mut end_len_buf = [0u8; 4];
+    if std::io::Read::read_exact(&mut cursor, &mut end_len_buf).is_err() {
+        writer.write_all(&0u32.to_le_bytes()).await?;
+        return Ok(());
+    }
+    let end_len = u32::from_le_bytes(end_len_buf) as usize;
+    let mut end_key = vec![0u8; end_len];
+    if std::io::Read::read_exact(&mut cursor, &mut end_key).is_err() {
+        writer.write_all(&0u32.to_le_bytes()).await?;
+        return Ok(());
+    }
+
+    let mut limit_buf = [0u8; 4];
+    if std::io::Read::read_exact(&mut cursor, &mut limit_buf).is_err() {
+        writer.write_all(&0u32.to_le_bytes()).await?;
+        return Ok(());
+    }
+    let limit = u32::from_le_bytes(limit_buf) as usize;
+
+    let (results, continuation) = db.range_scan(&start_key, &end_key, limit);
+
+    writer.write_all(&(results.len() as u32).to_le_bytes()).await?;
+    for (id, payload) in results {
+        writer.write_all(Uuid::from_u128(id).as_bytes()).await?;
+        writer.write_all(&(payload.len() as u32).to_le_bytes()).await?;
+        writer.write_all(&payload).await?;
+    }
+
+    match continuation {
+        Some(key) => {
+            writer.write_all(&(key.len() as u32).to_le_bytes()).await?;
+            writer.write_all(&key).await?;
+        }
+        None => writer.write_all(&0u32.to_le_bytes()).await?,
+    }
+    Ok(())
+}
+
 async fn handle_compact<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], db: &Arc<ChronosDb>) -> std::io::Result<()> {
     // Protocol: [History Limit u64 (8b)]
     if data.len() != 8 {
@@ -286,7 +583,10 @@ async fn handle_compact<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8], d
     }).await;
 
     match res {
-        Ok(Ok(_)) => writer.write_all(b"OK").await?,
+        Ok(Ok(reclaimed)) => {
+            writer.write_all(b"OK").await?;
+            writer.write_all(&reclaimed.to_le_bytes()).await?;
+        },
         Ok(Err(e)) => {
             eprintln!("Compaction Failed: {}", e);
             writer.write_all(b"ER").await?;
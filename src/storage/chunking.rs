@@ -0,0 +1,321 @@
+// src/storage/chunking.rs
+//
+// Content-defined chunking (CDC) for record payloads, as Garage does for
+// its block store. Splitting on a rolling content fingerprint instead of
+// fixed offsets means a payload that's mostly unchanged between versions
+// re-chunks to mostly the same boundaries, so `Segment` only has to store
+// (and a caller only has to dedup) the bytes that actually changed.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Target average chunk size (8 KiB): a boundary is cut whenever the
+/// fingerprint's masked bits are all zero, which happens with probability
+/// `1 / (mask + 1)` per byte. 13 bits gives a mask sized to that average.
+const TARGET_SIZE: usize = 8 * 1024;
+const AVG_MASK_BITS: u32 = 13;
+
+/// FastCDC "normalized chunking": below the target average, cut on the
+/// stricter `MASK_SMALL` (two extra bits set, so a boundary is four times
+/// less likely), which discourages premature small chunks; at or past the
+/// average, switch to the more permissive `MASK_LARGE` (two fewer bits) so a
+/// boundary is found quickly and sizes converge back toward `TARGET_SIZE`
+/// instead of drifting to `MAX_CHUNK_SIZE`.
+const MASK_SMALL: u64 = (1u64 << (AVG_MASK_BITS + 2)) - 1;
+const MASK_LARGE: u64 = (1u64 << (AVG_MASK_BITS - 2)) - 1;
+
+/// Never cut below this size, so small perturbations don't shatter a
+/// payload into a flood of tiny chunks.
+const MIN_CHUNK_SIZE: usize = 2 * 1024;
+
+/// Always cut at this size even if the fingerprint never lands on a
+/// boundary, bounding the worst case chunk size.
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+pub type ChunkHash = [u8; 32];
+
+/// Fixed 256-entry gear table mapping each byte value to a pseudo-random
+/// 64-bit weight. The table must be identical on every node (so identical
+/// payloads cut at the same boundaries everywhere), so it's generated once
+/// from a fixed seed rather than pulled from a `rand` dependency.
+fn gear_table() -> &'static [u64; 256] {
+    static TABLE: OnceLock<[u64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut seed: u64 = 0x9E3779B97F4A7C15;
+        let mut table = [0u64; 256];
+        for slot in table.iter_mut() {
+            // splitmix64
+            seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            *slot = z ^ (z >> 31);
+        }
+        table
+    })
+}
+
+/// Splits `data` into content-defined chunks using a gear-hash rolling
+/// fingerprint: for each byte, `fp = (fp << 1).wrapping_add(GEAR[byte])`.
+/// Below `TARGET_SIZE`, a boundary needs `fp & MASK_SMALL == 0`; at or past
+/// it, the easier `fp & MASK_LARGE == 0` applies instead (normalized
+/// chunking - see the mask constants above). `MIN_CHUNK_SIZE` is never cut
+/// below, `MAX_CHUNK_SIZE` is always cut at.
+pub fn split_chunks(data: &[u8]) -> Vec<&[u8]> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let gear = gear_table();
+    let mut chunks = Vec::new();
+    let mut start = 0usize;
+    let mut fp: u64 = 0;
+
+    for i in 0..data.len() {
+        fp = (fp << 1).wrapping_add(gear[data[i] as usize]);
+        let len = i - start + 1;
+
+        if len < MIN_CHUNK_SIZE {
+            continue;
+        }
+
+        let mask = if len < TARGET_SIZE { MASK_SMALL } else { MASK_LARGE };
+        if len >= MAX_CHUNK_SIZE || fp & mask == 0 {
+            chunks.push(&data[start..=i]);
+            start = i + 1;
+            fp = 0;
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(&data[start..]);
+    }
+
+    chunks
+}
+
+pub fn hash_chunk(chunk: &[u8]) -> ChunkHash {
+    *blake3::hash(chunk).as_bytes()
+}
+
+/// Refcounted table of content-addressed chunks shared across every record
+/// version a `Segment` holds. A chunk's bytes are written once no matter
+/// how many records - or how many historical versions of the same record -
+/// reference them.
+#[derive(Default, Debug)]
+pub struct ChunkStore {
+    chunks: HashMap<ChunkHash, (Vec<u8>, u32)>,
+}
+
+impl ChunkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuilds a store's chunk bytes from the on-disk log `Segment` keeps
+    /// alongside its segment file (see `Segment`'s `.chunks` sidecar),
+    /// written as repeated `[hash:32][len:u32][bytes]` records. Every
+    /// chunk's refcount starts at 0 - the log itself doesn't track who
+    /// still references what, so callers rebuild that separately via
+    /// `adopt` once they know which records survived recovery.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut chunks = HashMap::new();
+        if path.exists() {
+            let mut reader = BufReader::new(File::open(path)?);
+            loop {
+                let mut hash = [0u8; 32];
+                match reader.read_exact(&mut hash) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e),
+                }
+                let mut len_buf = [0u8; 4];
+                reader.read_exact(&mut len_buf)?;
+                let mut bytes = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+                reader.read_exact(&mut bytes)?;
+                chunks.insert(hash, (bytes, 0u32));
+            }
+        }
+        Ok(Self { chunks })
+    }
+
+    /// Bumps the refcount of every hash in `hashes` by one, without
+    /// touching their bytes. Used after `load` to turn "every chunk this
+    /// segment's log has ever held" into "every chunk its surviving
+    /// records still reference". A hash with no matching bytes in the log
+    /// is left absent rather than inserted as a phantom entry; reading it
+    /// later surfaces through `get`'s error instead of here.
+    pub fn adopt(&mut self, hashes: &[ChunkHash]) {
+        for hash in hashes {
+            if let Some((_, count)) = self.chunks.get_mut(hash) {
+                *count += 1;
+            }
+        }
+    }
+
+    /// Splits `payload` into chunks, inserting ones not already present and
+    /// bumping the refcount of ones that are. Returns the ordered hashes
+    /// needed to reassemble `payload` via `get`, plus the bytes of any
+    /// chunk that was new to this store - the caller (`Segment`) appends
+    /// those to its on-disk chunk log so the payload survives a restart.
+    pub fn put(&mut self, payload: &[u8]) -> (Vec<ChunkHash>, Vec<(ChunkHash, Vec<u8>)>) {
+        let mut hashes = Vec::new();
+        let mut new_chunks = Vec::new();
+
+        for chunk in split_chunks(payload) {
+            let hash = hash_chunk(chunk);
+            match self.chunks.entry(hash) {
+                std::collections::hash_map::Entry::Occupied(mut e) => {
+                    e.get_mut().1 += 1;
+                }
+                std::collections::hash_map::Entry::Vacant(e) => {
+                    e.insert((chunk.to_vec(), 1));
+                    new_chunks.push((hash, chunk.to_vec()));
+                }
+            }
+            hashes.push(hash);
+        }
+
+        (hashes, new_chunks)
+    }
+
+    /// Reassembles a payload by concatenating its chunks in order. Errors
+    /// instead of silently omitting bytes if a hash has no matching chunk -
+    /// that's payload data loss, not something a caller should get to treat
+    /// as an empty read.
+    pub fn get(&self, hashes: &[ChunkHash]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for hash in hashes {
+            match self.chunks.get(hash) {
+                Some((bytes, _)) => out.extend_from_slice(bytes),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("ChunkStore: missing chunk {}", hex_prefix(hash)),
+                    ));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Decrements the refcount of every hash in `hashes`, dropping a
+    /// chunk's bytes entirely once nothing references it anymore. Called
+    /// for the chunks backing a record version that Copy-GC is about to
+    /// prune, so superseded history doesn't keep otherwise-dead chunks
+    /// alive forever.
+    pub fn release(&mut self, hashes: &[ChunkHash]) {
+        for hash in hashes {
+            let mut drop_chunk = false;
+            if let Some((_, count)) = self.chunks.get_mut(hash) {
+                *count = count.saturating_sub(1);
+                drop_chunk = *count == 0;
+            }
+            if drop_chunk {
+                self.chunks.remove(hash);
+            }
+        }
+    }
+}
+
+/// First few bytes of a chunk hash, hex-encoded, for error messages -
+/// enough to recognize a specific chunk without pulling in a hex crate
+/// dependency just for this.
+fn hex_prefix(hash: &ChunkHash) -> String {
+    hash.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_chunks_respects_size_bounds_and_reassembles() {
+        let data: Vec<u8> = (0..20 * 1024).map(|i| (i % 251) as u8).collect();
+        let chunks = split_chunks(&data);
+
+        assert!(!chunks.is_empty());
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= MIN_CHUNK_SIZE);
+            assert!(chunk.len() <= MAX_CHUNK_SIZE);
+        }
+
+        let reassembled: Vec<u8> = chunks.concat();
+        assert_eq!(reassembled, data);
+    }
+
+    #[test]
+    fn identical_content_dedups_to_the_same_chunk() {
+        let mut store = ChunkStore::new();
+        let payload = vec![7u8; 10 * 1024];
+
+        let (hashes_a, new_a) = store.put(&payload);
+        let count_after_first = store.len();
+        let (hashes_b, new_b) = store.put(&payload);
+
+        assert_eq!(hashes_a, hashes_b);
+        assert!(!new_a.is_empty(), "the first put must report its chunks as new");
+        assert!(new_b.is_empty(), "re-inserting identical content must not report any new chunks");
+        assert_eq!(store.len(), count_after_first, "re-inserting identical content must not add new chunks");
+        assert_eq!(store.get(&hashes_a).unwrap(), payload);
+    }
+
+    #[test]
+    fn release_drops_a_chunk_once_its_last_reference_is_gone() {
+        let mut store = ChunkStore::new();
+        let payload = vec![9u8; 3 * 1024];
+
+        let (hashes, _new) = store.put(&payload);
+        assert!(store.len() > 0);
+
+        store.release(&hashes);
+        assert_eq!(store.len(), 0);
+        assert!(store.get(&hashes).is_err(), "a released chunk must no longer be readable");
+    }
+
+    #[test]
+    fn get_errors_on_a_hash_with_no_matching_chunk() {
+        let store = ChunkStore::new();
+        let bogus_hash = hash_chunk(b"never inserted");
+
+        assert!(store.get(&[bogus_hash]).is_err());
+    }
+
+    #[test]
+    fn load_rebuilds_bytes_from_a_chunk_log_and_adopt_restores_refcounts() {
+        let dir = std::env::temp_dir().join(format!("chronos-chunkstore-load-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log_path = dir.join("segment.chunks");
+
+        let payload = vec![3u8; 5 * 1024];
+        let mut writer_store = ChunkStore::new();
+        let (hashes, new_chunks) = writer_store.put(&payload);
+
+        let mut log = std::fs::File::create(&log_path).unwrap();
+        for (hash, bytes) in &new_chunks {
+            use std::io::Write;
+            log.write_all(hash).unwrap();
+            log.write_all(&(bytes.len() as u32).to_le_bytes()).unwrap();
+            log.write_all(bytes).unwrap();
+        }
+        drop(log);
+
+        let mut reloaded = ChunkStore::load(&log_path).unwrap();
+        assert_eq!(reloaded.get(&hashes).unwrap(), payload);
+
+        reloaded.adopt(&hashes);
+        reloaded.release(&hashes);
+        assert!(reloaded.get(&hashes).is_ok(), "refcount adopted from records must survive one release");
+        reloaded.release(&hashes);
+        assert!(reloaded.get(&hashes).is_err(), "chunk must be gone once its adopted refcount reaches zero");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
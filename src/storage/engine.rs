@@ -0,0 +1,28 @@
+use std::io;
+use crate::model::Record;
+
+/// Common interface for the on-disk storage backend.
+///
+/// `offset` is an opaque handle returned by `append` and handed back to
+/// `read` later (e.g. the index stores these per record id). Each engine is
+/// free to interpret it however fits its on-disk layout - a byte offset for
+/// the append-only log, a generated monotonic id for an LSM store, etc.
+pub trait StorageEngine: Send + std::fmt::Debug {
+    fn append(&mut self, record: &Record) -> io::Result<u64>;
+    fn read(&self, offset: u64) -> io::Result<Record>;
+
+    /// Every record currently stored, in engine-native order, paired with
+    /// the offset that can be used to `read` it again later.
+    fn scan(&self) -> io::Result<Vec<(u64, Record)>>;
+
+    /// Force durable persistence of anything buffered in memory.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Releases whatever content-defined chunks back `offset`'s payload, so
+    /// a superseded record version stops keeping its chunks alive. Only
+    /// meaningful for backends that actually chunk+dedup payloads (see
+    /// `Segment`); backends without a chunk table have nothing to release.
+    fn release_payload(&mut self, _offset: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+mod engine;
+mod segment;
+mod lsm;
+mod chunking;
+
+pub use engine::StorageEngine;
+pub use segment::Segment;
+pub use lsm::LsmEngine;
+
+use std::io;
+use std::path::Path;
+
+/// Selects which on-disk format a database is opened with. Chosen once at
+/// `ChronosDb::new` time and fixed for the life of that storage directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineKind {
+    /// The original append-only, length-prefixed log. Simple, sequential
+    /// writes; space is only reclaimed via explicit Copy-GC (`compact`).
+    Segment,
+    /// Embedded compacting LSM store (`sled`). Background compaction keeps
+    /// disk usage bounded under high-cardinality update workloads.
+    Lsm,
+}
+
+/// Open a fresh `StorageEngine` of the given kind at `path`.
+pub fn open_engine(kind: EngineKind, path: &Path, strict: bool) -> io::Result<Box<dyn StorageEngine>> {
+    open_engine_with_key(kind, path, strict, None)
+}
+
+/// Same as `open_engine`, but with an optional master key to seal record
+/// frames at rest (see `crypto`, `Segment::new_encrypted`). Only the
+/// `Segment` backend currently supports it; `master_key` is ignored for
+/// `EngineKind::Lsm`, which has no encryption-at-rest story of its own yet.
+pub fn open_engine_with_key(
+    kind: EngineKind,
+    path: &Path,
+    strict: bool,
+    master_key: Option<&[u8]>,
+) -> io::Result<Box<dyn StorageEngine>> {
+    match (kind, master_key) {
+        (EngineKind::Segment, Some(key)) => Ok(Box::new(Segment::new_encrypted(path, strict, key)?)),
+        (EngineKind::Segment, None) => Ok(Box::new(Segment::new(path, strict)?)),
+        (EngineKind::Lsm, _) => Ok(Box::new(LsmEngine::new(path, strict)?)),
+    }
+}
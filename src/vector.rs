@@ -2,9 +2,39 @@
 pub enum Metric {
     Euclidean,
     Cosine,
+    /// Negative dot product, so the "lower is closer" convention still
+    /// holds. Skips the two `sqrt`/norm passes Cosine needs - appropriate
+    /// when vectors are already normalized upstream (e.g. most embedding
+    /// models) and the caller wants the cheapest possible comparison.
+    InnerProduct,
 }
 
 impl Metric {
+    /// Single-byte tag for `HnswIndex::save`/`save_encrypted`, so a
+    /// persisted index remembers which metric it was built with instead of
+    /// a reload silently defaulting back to `Cosine`.
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Metric::Euclidean => 0,
+            Metric::Cosine => 1,
+            Metric::InnerProduct => 2,
+        }
+    }
+
+    /// Inverse of `to_byte`. Fails cleanly on a tag from neither a newer
+    /// nor an older format this build knows about.
+    pub fn from_byte(byte: u8) -> std::io::Result<Self> {
+        match byte {
+            0 => Ok(Metric::Euclidean),
+            1 => Ok(Metric::Cosine),
+            2 => Ok(Metric::InnerProduct),
+            other => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Metric: unknown tag byte {}", other),
+            )),
+        }
+    }
+
     /// Calculate distance. LOWER is ALWAYS closer/better.
     /// Optimized: Returns SQUARED distance for Euclidean to avoid expensive sqrt().
     ///
@@ -86,6 +116,24 @@ impl Metric {
                 if norm_a == 0.0 || norm_b == 0.0 { return 1.0; }
                 1.0 - (dot / (norm_a.sqrt() * norm_b.sqrt()))
             }
+            Metric::InnerProduct => {
+                let mut dot = 0.0;
+
+                let chunks = a.chunks_exact(8);
+                let b_chunks = b.chunks_exact(8);
+                let remainder_start = a.len() - a.len() % 8;
+
+                for (ac, bc) in chunks.zip(b_chunks) {
+                    dot += ac[0]*bc[0] + ac[1]*bc[1] + ac[2]*bc[2] + ac[3]*bc[3] +
+                    ac[4]*bc[4] + ac[5]*bc[5] + ac[6]*bc[6] + ac[7]*bc[7];
+                }
+
+                for i in remainder_start..a.len() {
+                    dot += a[i] * b[i];
+                }
+
+                -dot
+            }
         }
     }
 }